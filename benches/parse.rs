@@ -0,0 +1,46 @@
+//! Parser throughput benchmark.
+//!
+//! Run with `cargo bench --bench parse`. Each case is parsed repeatedly and the
+//! best time per iteration is reported, along with the resulting throughput.
+
+use ansi_escapers::interpreter::{AnsiParser, parse_ansi_annotated};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+const SAMPLES: u32 = 10;
+
+/// Time `f` and return the fastest per-iteration duration over all samples.
+fn bench<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    let mut best = Duration::MAX;
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            f();
+        }
+        best = best.min(start.elapsed() / ITERATIONS);
+    }
+    let throughput = bytes as f64 / best.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<40} {:>12?} {:>10.1} MiB/s", name, best, throughput);
+}
+
+fn main() {
+    let ascii = "The quick brown fox jumps over the lazy dog. ".repeat(2000);
+    let unicode = "\u{65E5}\u{672C}\u{8A9E}\u{306E}\u{30C6}\u{30AD}\u{30B9}\u{30C8} ".repeat(2000);
+    let styled = "\x1B[1;31merror\x1B[0m: something \x1B[4mwent\x1B[24m wrong\n".repeat(1000);
+    let dense = "\x1B[38;2;255;128;0mx\x1B[0m".repeat(5000);
+
+    for (name, input) in [
+        ("ascii, no escapes", &ascii),
+        ("unicode, no escapes", &unicode),
+        ("log lines with SGR", &styled),
+        ("one escape per char", &dense),
+    ] {
+        bench(&format!("parse_annotated/{}", name), input.len(), || {
+            black_box(parse_ansi_annotated(black_box(input)));
+        });
+        bench(&format!("events/{}", name), input.len(), || {
+            black_box(AnsiParser::new(black_box(input)).events().count());
+        });
+    }
+}
@@ -10,7 +10,7 @@
 //!
 //! ```rust
 
-//! use ansiescapers::{creator::*, interpreter::*, types::*};
+//! use ansi_escapers::{creator::*, interpreter::*, screen::*, types::*};
 
 //! ```
 //!
@@ -22,6 +22,12 @@ mod ansi_creator;
 
 mod ansi_interpreter;
 
+mod ansi_paint;
+
+mod ansi_render;
+
+mod ansi_screen;
+
 mod ansi_types;
 
 pub mod creator {
@@ -34,7 +40,30 @@ pub mod types {
     pub use crate::ansi_escape::ansi_types::*;
 }
 
+// Re-export all public items from screen
+pub mod screen {
+    pub use crate::ansi_escape::ansi_screen::*;
+}
+
+// Re-export all public items from paint
+pub mod paint {
+    pub use crate::ansi_escape::ansi_paint::*;
+}
+
+// Re-export all public items from render
+pub mod render {
+    pub use crate::ansi_escape::ansi_render::*;
+}
+
 // Re-export all public items from interpreter
 pub mod interpreter {
     pub use crate::ansi_escape::ansi_interpreter::*;
 }
+
+// Flatten the main types into the crate root
+pub use creator::*;
+pub use interpreter::*;
+pub use paint::*;
+pub use render::*;
+pub use screen::*;
+pub use types::*;
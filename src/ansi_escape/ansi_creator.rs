@@ -3,14 +3,92 @@
 //! API for producing ANSI escape codes, querying environment capabilities,
 //! and supporting text formatting, cursor movement, clearing the terminal, and more.
 
+use super::ansi_interpreter::{AnsiParser, Event, parse_ansi_annotated};
 use super::ansi_types::{
-    AnsiEscape, Color, CursorMove, DeviceControl, Erase, EraseMode, SgrAttribute,
+    AnsiEscape, Color, CursorMove, DeviceControl, Erase, EraseMode, PromptMark, Scroll,
+    SgrAttribute, TabClear, TabOp,
 };
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// `ESC [ 0 m`: reset all attributes.
+pub const SGR_RESET: &str = "\x1B[0m";
+/// `ESC [ m`: reset all attributes, in the compact form.
+pub const SGR_RESET_COMPACT: &str = "\x1B[m";
+/// `ESC [ 1 m`: bold.
+pub const SGR_BOLD: &str = "\x1B[1m";
+/// `ESC [ 2 m`: faint.
+pub const SGR_FAINT: &str = "\x1B[2m";
+/// `ESC [ 3 m`: italic.
+pub const SGR_ITALIC: &str = "\x1B[3m";
+/// `ESC [ 4 m`: underline.
+pub const SGR_UNDERLINE: &str = "\x1B[4m";
+/// `ESC [ 5 m`: slow blink.
+pub const SGR_BLINK_SLOW: &str = "\x1B[5m";
+/// `ESC [ 6 m`: rapid blink.
+pub const SGR_BLINK_RAPID: &str = "\x1B[6m";
+/// `ESC [ 7 m`: reverse video.
+pub const SGR_REVERSE: &str = "\x1B[7m";
+/// `ESC [ 8 m`: conceal.
+pub const SGR_CONCEAL: &str = "\x1B[8m";
+/// `ESC [ 9 m`: crossed out.
+pub const SGR_CROSSED_OUT: &str = "\x1B[9m";
+/// `ESC [ 53 m`: overline.
+pub const SGR_OVERLINE: &str = "\x1B[53m";
+/// `ESC [ 55 m`: not overlined.
+pub const SGR_NOT_OVERLINE: &str = "\x1B[55m";
+/// `ESC [ 25 m`: not blinking.
+pub const SGR_NOT_BLINKING: &str = "\x1B[25m";
+/// `ESC [ 51 m`: framed.
+pub const SGR_FRAMED: &str = "\x1B[51m";
+/// `ESC [ 52 m`: encircled.
+pub const SGR_ENCIRCLED: &str = "\x1B[52m";
+/// `ESC [ 54 m`: neither framed nor encircled.
+pub const SGR_NOT_FRAMED_ENCIRCLED: &str = "\x1B[54m";
+/// `ESC [ 39 m`: default foreground color.
+pub const SGR_DEFAULT_FOREGROUND: &str = "\x1B[39m";
+/// `ESC [ 49 m`: default background color.
+pub const SGR_DEFAULT_BACKGROUND: &str = "\x1B[49m";
+/// `ESC [ 59 m`: default underline color.
+pub const SGR_DEFAULT_UNDERLINE_COLOR: &str = "\x1B[59m";
+
+/// `ESC [ s`: save the cursor position.
+pub const SAVE_CURSOR: &str = "\x1B[s";
+/// `ESC [ u`: restore the saved cursor position.
+pub const RESTORE_CURSOR: &str = "\x1B[u";
+/// `ESC [ ? 25 l`: hide the cursor.
+pub const HIDE_CURSOR: &str = "\x1B[?25l";
+/// `ESC [ ? 25 h`: show the cursor.
+pub const SHOW_CURSOR: &str = "\x1B[?25h";
+/// `ESC [ ? 12 h`: enable cursor blinking.
+pub const CURSOR_BLINK_ON: &str = "\x1B[?12h";
+/// `ESC [ ? 12 l`: disable cursor blinking.
+pub const CURSOR_BLINK_OFF: &str = "\x1B[?12l";
+/// `ESC [ ? 1049 h`: switch to the alternate screen buffer.
+pub const ENTER_ALT_SCREEN: &str = "\x1B[?1049h";
+/// `ESC [ ? 1049 l`: switch back to the main screen buffer.
+pub const EXIT_ALT_SCREEN: &str = "\x1B[?1049l";
+/// `ESC [ ! p`: soft terminal reset (DECSTR).
+pub const SOFT_RESET: &str = "\x1B[!p";
+/// `ESC ] R`: reset the Linux console palette.
+pub const LINUX_PALETTE_RESET: &str = "\x1B]R";
+/// `ESC c`: full terminal reset (RIS).
+pub const FULL_RESET: &str = "\x1Bc";
+/// `ESC M`: reverse index (RI).
+pub const REVERSE_INDEX: &str = "\x1BM";
 
 /// Query the environment for ANSI support and capabilities.
 /// Describes the ANSI capabilities of the current environment (terminal).
 ///
-/// Use [`AnsiEnvironment::detect`] to query the current environment.
+/// Use [`AnsiEnvironment::detect`] to query the current environment, or build one
+/// with a struct literal to force a specific capability level.
+///
+/// [`AnsiEnvironment::default`] does not perform detection: it returns a conservative
+/// environment with every capability set to `false`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct AnsiEnvironment {
     /// True if ANSI escape codes are supported.
     pub supports_ansi: bool,
@@ -18,66 +96,247 @@ pub struct AnsiEnvironment {
     pub supports_truecolor: bool,
     /// True if 8-bit (256 color) is supported.
     pub supports_8bit_color: bool,
+    /// True if color output is suppressed (e.g. via `NO_COLOR`).
+    pub color_disabled: bool,
+    /// True if OSC 8 hyperlinks are supported.
+    pub supports_hyperlinks: bool,
+    /// True if setting the clipboard with OSC 52 is supported.
+    pub supports_osc52: bool,
+    /// Terminal width in columns, if it could be determined.
+    pub cols: Option<u16>,
+    /// Terminal height in rows, if it could be determined.
+    pub rows: Option<u16>,
     // Add more capabilities as needed
 }
 impl AnsiEnvironment {
-    /// Query the current environment for ANSI capabilities.
-
     /// Query the current environment for ANSI capabilities.
     ///
     /// This will check for ANSI, 8-bit, and truecolor support using platform-specific logic.
+    ///
+    /// The following environment variables are honored, in order of precedence:
+    /// 1. `NO_COLOR` (present and non-empty): color output is disabled. Non-color
+    ///    attributes (bold, cursor movement, ...) are unaffected.
+    /// 2. `CLICOLOR_FORCE` (set to anything other than `0`): ANSI output is enabled even
+    ///    when stdout is not a tty, e.g. when redirected to a file.
+    /// 3. Otherwise, ANSI support requires stdout to be a tty.
+    ///
+    /// Hyperlink and OSC 52 clipboard support are guessed from the terminal's identity
+    /// (`TERM`, `TERM_PROGRAM`, `TERM_PROGRAM_VERSION`, `VTE_VERSION`, `WT_SESSION`), as
+    /// terminals do not advertise them. Unknown terminals, including plain xterm, are
+    /// assumed to support neither.
+    ///
+    /// The terminal size is read from `COLUMNS` and `LINES` and, on Unix, falls back to
+    /// asking the terminal on stdout (`TIOCGWINSZ`). It is left as `None` when neither
+    /// gives an answer.
+    #[cfg(feature = "std")]
     pub fn detect() -> Self {
         // Use atty to check if stdout is a tty
         let is_tty = atty::is(atty::Stream::Stdout);
+        let mut env = Self::detect_with(is_tty, |name| {
+            std::env::var_os(name).map(|v| v.to_string_lossy().into_owned())
+        });
+        #[cfg(unix)]
+        if let Some((cols, rows)) = Self::window_size() {
+            env.cols = env.cols.or(Some(cols));
+            env.rows = env.rows.or(Some(rows));
+        }
+        env
+    }
 
-        // Platform-specific logic
-        #[cfg(windows)]
-        let (supports_ansi, supports_truecolor, supports_8bit_color) = {
-            // Windows 10+ supports ANSI if ENABLE_VIRTUAL_TERMINAL_PROCESSING is enabled.
-            // For now, assume Windows 10+ and that it's enabled if we're in a tty.
-            // For more robust detection, winapi could be used to check/enable the flag.
-            // Truecolor is supported in Windows Terminal, VSCode, and some others.
-            let supports_ansi = is_tty;
-            let supports_truecolor = std::env::var("WT_SESSION").is_ok()
-                || std::env::var("TERM_PROGRAM")
-                    .map(|v| v == "vscode")
-                    .unwrap_or(false)
-                || std::env::var("TERM")
-                    .map(|v| v.contains("xterm") || v.contains("truecolor"))
-                    .unwrap_or(false);
-            let supports_8bit_color = supports_ansi;
-            (supports_ansi, supports_truecolor, supports_8bit_color)
+    /// Internal: query the size of the terminal on stdout as `(cols, rows)`.
+    #[cfg(all(feature = "std", unix))]
+    fn window_size() -> Option<(u16, u16)> {
+        let mut size = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
         };
+        // SAFETY: TIOCGWINSZ only writes a `winsize` through the pointer, which is valid
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        (result == 0 && size.ws_col > 0 && size.ws_row > 0).then_some((size.ws_col, size.ws_row))
+    }
 
-        #[cfg(not(windows))]
-        let (supports_ansi, supports_truecolor, supports_8bit_color) = {
-            // On Unix, check TERM and COLORTERM
-            let term = std::env::var("TERM").unwrap_or_default();
-            let colorterm = std::env::var("COLORTERM").unwrap_or_default();
-            let supports_ansi = is_tty && term != "dumb" && !term.is_empty();
-            let supports_truecolor = colorterm == "truecolor"
-                || colorterm == "24bit"
-                || term.contains("truecolor")
-                || term.contains("24bit");
-            let supports_8bit_color = term.contains("256color") || supports_truecolor;
-            (supports_ansi, supports_truecolor, supports_8bit_color)
+    /// Compute capabilities from explicitly provided inputs, without reading the environment.
+    ///
+    /// This contains the pure decision logic used by [`AnsiEnvironment::detect`]:
+    /// * `is_tty` - Whether stdout is a terminal.
+    /// * `term` - The value of `TERM`.
+    /// * `colorterm` - The value of `COLORTERM`.
+    /// * `wt_session` - Whether `WT_SESSION` is set (Windows Terminal).
+    /// * `term_program` - The value of `TERM_PROGRAM`.
+    ///
+    /// `NO_COLOR` and `CLICOLOR_FORCE` are applied by [`AnsiEnvironment::detect`] on top of this.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::AnsiEnvironment;
+    /// let env = AnsiEnvironment::from_parts(true, Some("xterm-256color"), None, false, None);
+    /// assert!(env.supports_8bit_color);
+    /// ```
+    pub fn from_parts(
+        is_tty: bool,
+        term: Option<&str>,
+        colorterm: Option<&str>,
+        wt_session: bool,
+        term_program: Option<&str>,
+    ) -> Self {
+        let term = term.unwrap_or_default();
+        let colorterm = colorterm.unwrap_or_default();
+
+        // Windows 10+ supports ANSI if ENABLE_VIRTUAL_TERMINAL_PROCESSING is enabled.
+        // For now, assume Windows 10+ and that it's enabled if we're in a tty.
+        // For more robust detection, winapi could be used to check/enable the flag.
+        // On Unix, TERM must name a real terminal.
+        let supports_ansi = is_tty && term != "dumb" && (cfg!(windows) || !term.is_empty());
+
+        // Truecolor is advertised through COLORTERM/TERM, and is supported in
+        // Windows Terminal and VSCode regardless of TERM.
+        let supports_truecolor = colorterm == "truecolor"
+            || colorterm == "24bit"
+            || term.contains("truecolor")
+            || term.contains("24bit")
+            || wt_session
+            || term_program == Some("vscode")
+            || (cfg!(windows) && term.contains("xterm"));
+
+        let supports_8bit_color = if cfg!(windows) {
+            supports_ansi
+        } else {
+            term.contains("256color") || supports_truecolor
         };
 
         Self {
             supports_ansi,
             supports_truecolor,
             supports_8bit_color,
+            color_disabled: false,
+            supports_hyperlinks: false,
+            supports_osc52: false,
+            cols: None,
+            rows: None,
+        }
+    }
+
+    /// Internal: detection logic with the tty state and environment lookup injected.
+    fn detect_with(is_tty: bool, var: impl Fn(&str) -> Option<String>) -> Self {
+        let parts = Self::from_parts(
+            is_tty,
+            var("TERM").as_deref(),
+            var("COLORTERM").as_deref(),
+            var("WT_SESSION").is_some(),
+            var("TERM_PROGRAM").as_deref(),
+        );
+
+        // Honor the NO_COLOR convention (https://no-color.org)
+        let color_disabled = var("NO_COLOR").is_some_and(|v| !v.is_empty());
+        // Honor CLICOLOR_FORCE to emit ANSI codes even when not writing to a tty
+        let force = var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+
+        let supports_ansi = parts.supports_ansi || force;
+        let dimension = |name| {
+            var(name)
+                .and_then(|v| v.trim().parse::<u16>().ok())
+                .filter(|&n| n > 0)
+        };
+        let (supports_hyperlinks, supports_osc52) = if supports_ansi {
+            Self::osc_support(&var)
+        } else {
+            (false, false)
+        };
+
+        Self {
+            supports_ansi,
+            supports_truecolor: parts.supports_truecolor && !color_disabled,
+            supports_8bit_color: parts.supports_8bit_color && !color_disabled,
+            color_disabled,
+            supports_hyperlinks,
+            supports_osc52,
+            cols: dimension("COLUMNS"),
+            rows: dimension("LINES"),
+        }
+    }
+
+    /// Internal: guess `(supports_hyperlinks, supports_osc52)` from the terminal's identity.
+    fn osc_support(var: &impl Fn(&str) -> Option<String>) -> (bool, bool) {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let version = var("TERM_PROGRAM_VERSION").unwrap_or_default();
+        // "3.4.19" -> (3, 4); missing parts count as zero
+        let mut numbers = version.split('.').map(|v| v.parse::<u32>().unwrap_or(0));
+        let version = (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0));
+
+        // Terminals known to support both OSC 8 and OSC 52
+        let both = matches!(program.as_str(), "WezTerm" | "ghostty")
+            || (program == "iTerm.app" && version >= (3, 1))
+            || var("WT_SESSION").is_some()
+            || term == "xterm-kitty"
+            || term.starts_with("foot");
+        // VTE (GNOME Terminal, Tilix, ...) has hyperlinks since 0.50 but no OSC 52
+        let vte = var("VTE_VERSION")
+            .and_then(|v| v.parse::<u32>().ok())
+            .is_some_and(|v| v >= 5000);
+        let vscode = program == "vscode" && version >= (1, 72);
+        (both || vte || vscode, both)
+    }
+
+    /// The richest kind of color this environment can display.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiEnvironment, ColorLevel};
+    /// let env = AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_8bit_color: true,
+    ///     ..AnsiEnvironment::default()
+    /// };
+    /// assert_eq!(env.color_level(), ColorLevel::Ansi256);
+    /// assert!(env.color_level() >= ColorLevel::Basic16);
+    /// ```
+    pub fn color_level(&self) -> ColorLevel {
+        if !self.supports_ansi || self.color_disabled {
+            ColorLevel::None
+        } else if self.supports_truecolor {
+            ColorLevel::TrueColor
+        } else if self.supports_8bit_color {
+            ColorLevel::Ansi256
+        } else {
+            ColorLevel::Basic16
         }
     }
 }
 
+/// How many colors an environment can display, ordered from least to most capable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ColorLevel {
+    /// No color output (no ANSI support, or color disabled).
+    None,
+    /// The 16 named colors.
+    Basic16,
+    /// The 256-color palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
 /// API for producing ANSI escape codes.
 /// API for producing ANSI escape codes for formatting, color, cursor movement, and more.
 ///
 /// This is the main entry point for generating ANSI codes in a capability-aware way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AnsiCreator {
     /// The detected environment capabilities.
     pub env: AnsiEnvironment,
+    /// Emit resets as `ESC [ m` instead of `ESC [ 0 m`, saving a byte per reset.
+    pub compact_reset: bool,
+}
+
+#[cfg(feature = "std")]
+impl Default for AnsiCreator {
+    /// Same as [`AnsiCreator::new`]: the environment is detected.
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AnsiCreator {
@@ -88,54 +347,559 @@ impl AnsiCreator {
     /// use ansi_escapers::AnsiCreator;
     /// let creator = AnsiCreator::new();
     /// ```
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
+        Self::with_environment(AnsiEnvironment::detect())
+    }
+
+    /// Create a new `AnsiCreator` for an explicit set of capabilities, skipping detection.
+    ///
+    /// Useful for tests, or for writing output that will be viewed in a different terminal.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_8bit_color: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// ```
+    pub fn with_environment(env: AnsiEnvironment) -> Self {
         Self {
-            env: AnsiEnvironment::detect(),
+            env,
+            compact_reset: false,
+        }
+    }
+
+    /// Emit resets in the shorter `ESC [ m` form (an empty SGR parameter list means reset).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, SgrAttribute};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// })
+    /// .with_compact_reset(true);
+    /// assert_eq!(creator.sgr_code(SgrAttribute::Reset), "\x1B[m");
+    /// ```
+    pub fn with_compact_reset(mut self, enabled: bool) -> Self {
+        self.compact_reset = enabled;
+        self
+    }
+
+    /// Wrap a writer in an [`AnsiTerminal`] that writes this creator's codes to it directly.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, Color};
+    /// let mut term = AnsiCreator::new().with_writer(std::io::stdout());
+    /// term.set_fg(Color::Green)?;
+    /// term.print("ok\n")?;
+    /// term.reset()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_writer<W: std::io::Write>(self, writer: W) -> AnsiTerminal<W> {
+        AnsiTerminal {
+            writer,
+            creator: self,
         }
     }
 
     /// Format text with the given SGR (Select Graphic Rendition) attributes.
     ///
-    /// The text will be wrapped in the appropriate ANSI codes and reset at the end.
+    /// The text will be wrapped in the appropriate ANSI codes and reset at the end. If no
+    /// codes are produced (the environment does not support ANSI, or every attribute is
+    /// suppressed), the text is returned unchanged, with no trailing reset.
     ///
     /// # Example
     /// ```
-    /// use ansi_escapers::{AnsiCreator, SgrAttribute, Color};
-    /// let creator = AnsiCreator::new();
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, SgrAttribute, Color};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
     /// let s = creator.format_text("Hello", &[SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)]);
     /// ```
     pub fn format_text(&self, text: &str, attrs: &[SgrAttribute]) -> String {
+        if !self.env.supports_ansi {
+            return text.to_string();
+        }
         let mut code = String::new();
         for attr in attrs {
             code.push_str(&self.sgr_code(*attr));
         }
+        // Nothing was opened (e.g. only colors, with color disabled), so nothing to reset
+        if code.is_empty() {
+            return text.to_string();
+        }
         let reset = self.sgr_code(SgrAttribute::Reset);
         format!("{}{}{}", code, text, reset)
     }
 
-    /// Produce the ANSI escape code for a single SGR attribute.
+    /// Write the SGR codes for `attrs` to `w`, without building an intermediate string.
+    ///
+    /// Nothing is written if the environment does not support ANSI.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, SgrAttribute};
+    /// let mut out = Vec::new();
+    /// AnsiCreator::new().write_sgr(&mut out, &[SgrAttribute::Bold])?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_sgr(
+        &self,
+        w: &mut impl std::io::Write,
+        attrs: &[SgrAttribute],
+    ) -> std::io::Result<()> {
+        self.write_sgr_counted(w, attrs).map(|_| ())
+    }
+
+    /// Write `text` wrapped in the codes for `attrs` to `w`.
+    ///
+    /// Produces the same bytes as [`AnsiCreator::format_text`], without building an
+    /// intermediate string.
     ///
     /// # Example
     /// ```
+    /// use std::io::Write;
     /// use ansi_escapers::{AnsiCreator, SgrAttribute};
     /// let creator = AnsiCreator::new();
+    /// let mut out = std::io::stdout().lock();
+    /// creator.write_styled(&mut out, "done", &[SgrAttribute::Bold])?;
+    /// writeln!(out)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_styled(
+        &self,
+        w: &mut impl std::io::Write,
+        text: &str,
+        attrs: &[SgrAttribute],
+    ) -> std::io::Result<()> {
+        let opened = self.write_sgr_counted(w, attrs)?;
+        w.write_all(text.as_bytes())?;
+        // Like format_text, only reset if something was opened
+        if opened {
+            w.write_all(self.sgr_code(SgrAttribute::Reset).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Write the SGR codes for `attrs`, returning whether any bytes were written.
+    #[cfg(feature = "std")]
+    fn write_sgr_counted(
+        &self,
+        w: &mut impl std::io::Write,
+        attrs: &[SgrAttribute],
+    ) -> std::io::Result<bool> {
+        if !self.env.supports_ansi {
+            return Ok(false);
+        }
+        let mut written = false;
+        for attr in attrs {
+            let code = self.sgr_code(*attr);
+            w.write_all(code.as_bytes())?;
+            written |= !code.is_empty();
+        }
+        Ok(written)
+    }
+
+    /// Measure the display width of the text [`AnsiCreator::format_text`] would produce.
+    ///
+    /// Escape codes contribute no width, so the result only depends on `text`: wide
+    /// characters (e.g. CJK) count as two columns.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, SgrAttribute};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.measure("\u{65E5}\u{672C}", &[SgrAttribute::Bold]), 4);
+    /// ```
+    pub fn measure(&self, text: &str, attrs: &[SgrAttribute]) -> usize {
+        let _ = attrs;
+        text.width()
+    }
+
+    /// Cut styled text off after `max_cols` display columns, keeping its escape codes.
+    ///
+    /// Escape codes do not count toward the width. A wide character that would straddle
+    /// the limit is dropped entirely. If a style is still active where the text is cut, a
+    /// reset is appended. Codes are re-emitted by this creator, so colors are subject to its
+    /// environment's capabilities. Strings the parser does not model, such as OSC 8
+    /// hyperlinks, are copied from the input as they are; a hyperlink still open at the end
+    /// is closed.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.truncate_styled("\x1B[1mHello\x1B[0m", 3), "\x1B[1mHel\x1B[0m");
+    /// ```
+    pub fn truncate_styled(&self, input: &str, max_cols: usize) -> String {
+        let mut out = String::with_capacity(input.len());
+        let mut cols = 0;
+        let mut styled = false;
+        let mut linked = false;
+        let mut events = AnsiParser::new(input).with_preserve_unknown(true).events();
+        'events: loop {
+            let start = events.offset();
+            let Some(event) = events.next_or_skip() else {
+                break;
+            };
+            match event {
+                None => {
+                    let raw = &input[start..events.offset()];
+                    // `ESC ] 8 ; params ; URI ST` opens a hyperlink, an empty URI closes it
+                    if let Some((_, uri)) =
+                        raw.strip_prefix("\x1B]8;").and_then(|s| s.split_once(';'))
+                    {
+                        linked = !uri.trim_end_matches(['\x07', '\x1B', '\\']).is_empty();
+                    }
+                    out.push_str(raw);
+                }
+                Some(Event::Text(text)) => {
+                    for ch in text.chars() {
+                        let width = ch.width().unwrap_or(0);
+                        if cols + width > max_cols {
+                            break 'events;
+                        }
+                        cols += width;
+                        out.push(ch);
+                    }
+                }
+                Some(Event::Escape(escape)) => {
+                    if let AnsiEscape::Sgr(sgr) = escape {
+                        styled = sgr != SgrAttribute::Reset;
+                    }
+                    out.push_str(&self.escape_code(escape));
+                }
+            }
+        }
+        if styled {
+            out.push_str(&self.sgr_code(SgrAttribute::Reset));
+        }
+        if linked {
+            out.push_str("\x1B]8;;\x1B\\");
+        }
+        out
+    }
+
+    /// Pad styled text with spaces to `width` display columns, ignoring escape codes.
+    ///
+    /// Padding is placed outside any style: if `input` leaves a style active at its end, a
+    /// reset is added before the trailing padding. Text already at least `width` columns
+    /// wide is returned unchanged (apart from that reset).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Align};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.pad_styled("\x1B[1mab\x1B[0m", 4, Align::Right), "  \x1B[1mab\x1B[0m");
+    /// ```
+    pub fn pad_styled(&self, input: &str, width: usize, align: Align) -> String {
+        let pad = width.saturating_sub(parse_ansi_annotated(input).display_width());
+        let (left, right) = match align {
+            Align::Left => (0, pad),
+            Align::Right => (pad, 0),
+            Align::Center => (pad / 2, pad - pad / 2),
+        };
+        let mut styled = false;
+        for event in AnsiParser::new(input).events() {
+            if let Event::Escape(AnsiEscape::Sgr(sgr)) = event {
+                styled = sgr != SgrAttribute::Reset;
+            }
+        }
+        let mut out = String::with_capacity(input.len() + pad);
+        out.extend(core::iter::repeat_n(' ', left));
+        out.push_str(input);
+        if styled {
+            out.push_str(&self.sgr_code(SgrAttribute::Reset));
+        }
+        out.extend(core::iter::repeat_n(' ', right));
+        out
+    }
+
+    /// Format a tree of styled nodes, where each child inherits and extends its parent's style.
+    ///
+    /// When a styled node ends, the parent's style is restored via a reset followed by
+    /// re-applying the parent's attributes.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Color, SgrAttribute, StyledNode};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// let tree = StyledNode::styled(
+    ///     vec![SgrAttribute::Bold],
+    ///     vec![
+    ///         StyledNode::text("a"),
+    ///         StyledNode::styled(
+    ///             vec![SgrAttribute::Foreground(Color::Red)],
+    ///             vec![StyledNode::text("b")],
+    ///         ),
+    ///         StyledNode::text("c"),
+    ///     ],
+    /// );
+    /// let s = creator.format_nested(&tree);
+    /// ```
+    pub fn format_nested(&self, tree: &StyledNode) -> String {
+        let mut out = String::new();
+        self.format_node(tree, &[], &mut out);
+        out
+    }
+
+    /// Internal: render a node given the style inherited from its ancestors.
+    fn format_node(&self, node: &StyledNode, inherited: &[SgrAttribute], out: &mut String) {
+        match node {
+            StyledNode::Text(text) => out.push_str(text),
+            StyledNode::Styled { attrs, children } => {
+                let mut style = inherited.to_vec();
+                for attr in attrs {
+                    style.retain(|a| core::mem::discriminant(a) != core::mem::discriminant(attr));
+                    style.push(*attr);
+                    out.push_str(&self.sgr_code(*attr));
+                }
+                for child in children {
+                    self.format_node(child, &style, out);
+                }
+                // Restore the parent style on exit
+                if !attrs.is_empty() {
+                    out.push_str(&self.sgr_code(SgrAttribute::Reset));
+                    for attr in inherited {
+                        out.push_str(&self.sgr_code(*attr));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Map `value` within `min..=max` to a blue → green → red heatmap color.
+    ///
+    /// `value` is clamped to the range, and a degenerate range (`max <= min`) maps to blue.
+    /// The color is downgraded to the best form the environment supports.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Color};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_truecolor: true,
+    ///     supports_8bit_color: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.heatmap(10.0, 0.0, 10.0), Color::Rgb24 { r: 255, g: 0, b: 0 });
+    /// ```
+    pub fn heatmap(&self, value: f32, min: f32, max: f32) -> Color {
+        const STOPS: [(u8, u8, u8); 3] = [(0, 0, 255), (0, 255, 0), (255, 0, 0)];
+        let t = if max > min {
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let scaled = t * (STOPS.len() - 1) as f32;
+        let idx = (scaled as usize).min(STOPS.len() - 2);
+        let (r, g, b) = blend_rgb(STOPS[idx], STOPS[idx + 1], scaled - idx as f32);
+        self.downgrade_color(Color::Rgb24 { r, g, b })
+    }
+
+    /// Format `text` with the [`AnsiCreator::heatmap`] color for `value` as its background.
+    pub fn heatmap_cell(&self, value: f32, min: f32, max: f32, text: &str) -> String {
+        self.format_text(
+            text,
+            &[SgrAttribute::Background(self.heatmap(value, min, max))],
+        )
+    }
+
+    /// Color each character of `text` along a linear gradient from `from` to `to`.
+    ///
+    /// Colors are blended in RGB and downgraded to the best form the environment supports.
+    /// With [`RenderBudget::Coalesce`], a color code is only emitted when the downgraded
+    /// color changes, which keeps the output small when neighbouring characters end up
+    /// with the same color. Without ANSI or color support, `text` is returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Color, RenderBudget};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_truecolor: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// let s = creator.gradient_text("ab", Color::Red, Color::Blue, RenderBudget::Coalesce);
+    /// assert_eq!(s, "\x1B[38;2;205;0;0ma\x1B[38;2;0;0;238mb\x1B[0m");
+    /// ```
+    pub fn gradient_text(
+        &self,
+        text: &str,
+        from: Color,
+        to: Color,
+        budget: RenderBudget,
+    ) -> String {
+        let (from, to) = (from.to_rgb(), to.to_rgb());
+        let last = text.chars().count().saturating_sub(1).max(1);
+        self.paint_chars(text, budget, |i| {
+            let (r, g, b) = blend_rgb(from, to, i as f32 / last as f32);
+            Color::Rgb24 { r, g, b }
+        })
+    }
+
+    /// Color each character of `text` with successive hues of the rainbow, cycling every
+    /// `period` characters (a `period` of 0 is treated as 1).
+    ///
+    /// Like [`AnsiCreator::gradient_text`], colors are downgraded and `budget` controls
+    /// whether repeated colors are re-emitted.
+    pub fn rainbow_text(&self, text: &str, period: usize, budget: RenderBudget) -> String {
+        const STOPS: [(u8, u8, u8); 7] = [
+            (255, 0, 0),
+            (255, 255, 0),
+            (0, 255, 0),
+            (0, 255, 255),
+            (0, 0, 255),
+            (255, 0, 255),
+            (255, 0, 0),
+        ];
+        let period = period.max(1);
+        self.paint_chars(text, budget, |i| {
+            let scaled = (i % period) as f32 / period as f32 * (STOPS.len() - 1) as f32;
+            let idx = scaled as usize;
+            let (r, g, b) = blend_rgb(STOPS[idx], STOPS[idx + 1], scaled - idx as f32);
+            Color::Rgb24 { r, g, b }
+        })
+    }
+
+    /// Internal: give the `i`th character of `text` the foreground `color_at(i)`.
+    fn paint_chars(
+        &self,
+        text: &str,
+        budget: RenderBudget,
+        color_at: impl Fn(usize) -> Color,
+    ) -> String {
+        if !self.env.supports_ansi || self.env.color_disabled {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len() * 4);
+        let mut current = None;
+        for (i, ch) in text.chars().enumerate() {
+            let color = self.downgrade_color(color_at(i));
+            if budget == RenderBudget::PerChar || current != Some(color) {
+                out.push_str(&self.sgr_code(SgrAttribute::Foreground(color)));
+                current = Some(color);
+            }
+            out.push(ch);
+        }
+        if current.is_some() {
+            out.push_str(&self.sgr_code(SgrAttribute::Reset));
+        }
+        out
+    }
+
+    /// Produce the ANSI escape code for a single SGR attribute.
+    ///
+    /// Color attributes produce an empty string when the environment has color disabled.
+    ///
+    /// Codes without parameters (everything except explicit colors) are returned as
+    /// borrowed `&'static str`s, so they do not allocate.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, SgrAttribute};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
     /// let code = creator.sgr_code(SgrAttribute::Bold);
     /// ```
-    pub fn sgr_code(&self, attr: SgrAttribute) -> String {
-        match attr {
-            SgrAttribute::Reset => "\x1B[0m".to_string(),
-            SgrAttribute::Bold => "\x1B[1m".to_string(),
-            SgrAttribute::Faint => "\x1B[2m".to_string(),
-            SgrAttribute::Italic => "\x1B[3m".to_string(),
-            SgrAttribute::Underline => "\x1B[4m".to_string(),
-            SgrAttribute::BlinkSlow => "\x1B[5m".to_string(),
-            SgrAttribute::BlinkRapid => "\x1B[6m".to_string(),
-            SgrAttribute::Reverse => "\x1B[7m".to_string(),
-            SgrAttribute::Conceal => "\x1B[8m".to_string(),
-            SgrAttribute::CrossedOut => "\x1B[9m".to_string(),
-            SgrAttribute::Foreground(color) => self.fg_code(color),
-            SgrAttribute::Background(color) => self.bg_code(color),
-            SgrAttribute::UnderlineColor(color) => self.underline_color_code_explicit(color),
+    pub fn sgr_code(&self, attr: SgrAttribute) -> Cow<'static, str> {
+        if self.env.color_disabled
+            && matches!(
+                attr,
+                SgrAttribute::Foreground(_)
+                    | SgrAttribute::Background(_)
+                    | SgrAttribute::UnderlineColor(_)
+                    | SgrAttribute::DefaultForeground
+                    | SgrAttribute::DefaultBackground
+                    | SgrAttribute::DefaultUnderlineColor
+            )
+        {
+            return Cow::Borrowed("");
+        }
+        let code = match attr {
+            SgrAttribute::Reset if self.compact_reset => SGR_RESET_COMPACT,
+            SgrAttribute::Reset => SGR_RESET,
+            SgrAttribute::Bold => SGR_BOLD,
+            SgrAttribute::Faint => SGR_FAINT,
+            SgrAttribute::Italic => SGR_ITALIC,
+            SgrAttribute::Underline => SGR_UNDERLINE,
+            SgrAttribute::BlinkSlow => SGR_BLINK_SLOW,
+            SgrAttribute::BlinkRapid => SGR_BLINK_RAPID,
+            SgrAttribute::Reverse => SGR_REVERSE,
+            SgrAttribute::Conceal => SGR_CONCEAL,
+            SgrAttribute::CrossedOut => SGR_CROSSED_OUT,
+            SgrAttribute::Overline => SGR_OVERLINE,
+            SgrAttribute::NotOverline => SGR_NOT_OVERLINE,
+            SgrAttribute::NotBlinking => SGR_NOT_BLINKING,
+            SgrAttribute::Framed => SGR_FRAMED,
+            SgrAttribute::Encircled => SGR_ENCIRCLED,
+            SgrAttribute::NotFramedEncircled => SGR_NOT_FRAMED_ENCIRCLED,
+            SgrAttribute::DefaultForeground => SGR_DEFAULT_FOREGROUND,
+            SgrAttribute::DefaultBackground => SGR_DEFAULT_BACKGROUND,
+            SgrAttribute::DefaultUnderlineColor => SGR_DEFAULT_UNDERLINE_COLOR,
+            SgrAttribute::Foreground(color) => {
+                return self.fg_code(self.downgrade_color(color)).into();
+            }
+            SgrAttribute::Background(color) => {
+                return self.bg_code(self.downgrade_color(color)).into();
+            }
+            SgrAttribute::UnderlineColor(color) => {
+                return self
+                    .underline_color_code_explicit(self.downgrade_color(color))
+                    .into();
+            }
+            SgrAttribute::UnderlineStyle(kind) => {
+                return format!("\x1B[4:{}m", kind.param()).into();
+            }
+        };
+        Cow::Borrowed(code)
+    }
+
+    /// Start building a composite sequence of escape codes and text.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Color, SgrAttribute};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// let s = creator
+    ///     .sequence()
+    ///     .move_to(1, 1)
+    ///     .sgr(&[SgrAttribute::Foreground(Color::Red)])
+    ///     .text("Hello")
+    ///     .sgr(&[SgrAttribute::Reset])
+    ///     .build();
+    /// ```
+    pub fn sequence(&self) -> Sequence<'_> {
+        Sequence {
+            creator: self,
+            out: String::new(),
+            pending: Vec::new(),
         }
     }
 
@@ -148,6 +912,24 @@ impl AnsiCreator {
         format!("\x1B[{}m", code)
     }
 
+    /// Internal: downgrade a color to the best form the environment supports.
+    ///
+    /// 24-bit colors fall back to the nearest 8-bit palette entry, and 8-bit colors
+    /// fall back to the nearest of the 16 named colors.
+    fn downgrade_color(&self, color: Color) -> Color {
+        match color {
+            Color::Rgb24 { .. } if !self.env.supports_truecolor => {
+                if self.env.supports_8bit_color {
+                    Color::AnsiValue(color.to_ansi256())
+                } else {
+                    color.to_basic16()
+                }
+            }
+            Color::AnsiValue(_) if !self.env.supports_8bit_color => color.to_basic16(),
+            other => other,
+        }
+    }
+
     /// Internal: produce the ANSI escape code for a foreground color, using the most idiomatic form.
     fn fg_code(&self, color: Color) -> String {
         match color {
@@ -205,6 +987,45 @@ impl AnsiCreator {
         }
     }
 
+    /// Produce the foreground color code using the terminal's terminfo `setaf` capability.
+    ///
+    /// Looks up the terminfo entry for `$TERM`, falling back to the SGR form when the
+    /// entry or the capability is unavailable.
+    #[cfg(feature = "terminfo")]
+    pub fn fg_terminfo(&self, color: Color) -> String {
+        match terminfo::Database::from_env() {
+            Ok(db) => self.fg_terminfo_with(&db, color),
+            Err(_) => self.sgr_code(SgrAttribute::Foreground(color)).into_owned(),
+        }
+    }
+
+    /// Produce the foreground color code using the `setaf` capability of the given terminfo entry.
+    ///
+    /// 24-bit colors and indices beyond the entry's `colors` count fall back to the SGR form.
+    #[cfg(feature = "terminfo")]
+    pub fn fg_terminfo_with(&self, db: &terminfo::Database, color: Color) -> String {
+        use terminfo::capability::{MaxColors, SetAForeground};
+
+        let fallback = || self.sgr_code(SgrAttribute::Foreground(color)).into_owned();
+        if self.env.color_disabled {
+            return String::new();
+        }
+        let idx = match self.downgrade_color(color) {
+            Color::AnsiValue(idx) => idx,
+            Color::Rgb24 { .. } => return fallback(),
+            named => named.to_ansi256(),
+        };
+        if let Some(MaxColors(max)) = db.get::<MaxColors>()
+            && i32::from(idx) >= max
+        {
+            return fallback();
+        }
+        db.get::<SetAForeground>()
+            .and_then(|setaf| setaf.expand().color(idx).to_vec().ok())
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .unwrap_or_else(fallback)
+    }
+
     /// Produce the ANSI escape code for an 8-bit foreground color (SGR 38;5;N).
     ///
     /// # Arguments
@@ -276,6 +1097,8 @@ impl AnsiCreator {
             CursorMove::PreviousLine(n) => format!("\x1B[{}F", n),
             CursorMove::HorizontalAbsolute(n) => format!("\x1B[{}G", n),
             CursorMove::Position { row, col } => format!("\x1B[{};{}H", row, col),
+            CursorMove::VerticalAbsolute(n) => format!("\x1B[{}d", n),
+            CursorMove::VerticalRelative(n) => format!("\x1B[{}e", n),
         }
     }
 
@@ -290,144 +1113,714 @@ impl AnsiCreator {
         }
     }
 
-    /// Produce the ANSI escape code for device control.
+    /// Produce the ANSI escape code for scrolling the page.
     ///
     /// # Arguments
-    /// * `device` - The device control command.
-    pub fn device_code(&self, device: DeviceControl) -> String {
-        match device {
-            DeviceControl::SaveCursor => "\x1B[s".to_string(),
-            DeviceControl::RestoreCursor => "\x1B[u".to_string(),
-            DeviceControl::HideCursor => "\x1B[?25l".to_string(),
-            DeviceControl::ShowCursor => "\x1B[?25h".to_string(),
+    /// * `scroll` - The scroll direction and line count.
+    pub fn scroll_code(&self, scroll: Scroll) -> String {
+        match scroll {
+            Scroll::Up(n) => format!("\x1B[{}S", n),
+            Scroll::Down(n) => format!("\x1B[{}T", n),
         }
     }
 
-    /// Produce the ANSI escape code for any [`AnsiEscape`] enum variant.
+    /// Produce the ANSI escape code for a tab stop operation.
     ///
     /// # Arguments
-    /// * `code` - The escape code to convert to a string.
-    pub fn escape_code(&self, code: AnsiEscape) -> String {
-        match code {
-            AnsiEscape::Sgr(attr) => self.sgr_code(attr),
-            AnsiEscape::Cursor(movement) => self.cursor_code(movement),
-            AnsiEscape::Erase(erase) => self.erase_code(erase),
-            AnsiEscape::Device(device) => self.device_code(device),
+    /// * `op` - The tab operation (set, move forward/backward, or clear).
+    pub fn tab_code(&self, op: TabOp) -> String {
+        match op {
+            TabOp::Set => "\x1BH".to_string(),
+            TabOp::Forward(n) => format!("\x1B[{}I", n),
+            TabOp::Backward(n) => format!("\x1B[{}Z", n),
+            TabOp::Clear(TabClear::Current) => "\x1B[0g".to_string(),
+            TabOp::Clear(TabClear::All) => "\x1B[3g".to_string(),
         }
     }
-}
 
-/// Helper to convert EraseMode to its numeric code.
-fn erase_mode_num(mode: EraseMode) -> u8 {
-    match mode {
-        EraseMode::ToEnd => 0,
-        EraseMode::ToStart => 1,
-        EraseMode::All => 2,
+    /// Produce the ANSI escape code for device control.
+    ///
+    /// # Arguments
+    /// * `device` - The device control command.
+    ///
+    /// Codes without parameters are returned as borrowed `&'static str`s.
+    pub fn device_code(&self, device: DeviceControl) -> Cow<'static, str> {
+        let code = match device {
+            DeviceControl::SaveCursor => SAVE_CURSOR,
+            DeviceControl::RestoreCursor => RESTORE_CURSOR,
+            DeviceControl::HideCursor => HIDE_CURSOR,
+            DeviceControl::ShowCursor => SHOW_CURSOR,
+            DeviceControl::SetCursorBlink(true) => CURSOR_BLINK_ON,
+            DeviceControl::SetCursorBlink(false) => CURSOR_BLINK_OFF,
+            DeviceControl::EnterAltScreen => ENTER_ALT_SCREEN,
+            DeviceControl::ExitAltScreen => EXIT_ALT_SCREEN,
+            DeviceControl::LinuxPaletteReset => LINUX_PALETTE_RESET,
+            DeviceControl::SoftReset => SOFT_RESET,
+            DeviceControl::FullReset => FULL_RESET,
+            DeviceControl::ReverseIndex => REVERSE_INDEX,
+            DeviceControl::SetKittyKeyboard(flags) => {
+                return format!("\x1B[>{}u", flags.bits()).into();
+            }
+            DeviceControl::PopKittyKeyboard(count) => return format!("\x1B[<{}u", count).into(),
+            DeviceControl::SetCursorStyle(style) => {
+                return format!("\x1B[{} q", style.param()).into();
+            }
+            DeviceControl::AltScreen { mode, enabled } => {
+                return self.private_mode(mode.mode(), enabled).into();
+            }
+            DeviceControl::InitiateHighlightMouseTracking {
+                func,
+                start_col,
+                start_row,
+                first_row,
+                last_row,
+            } => {
+                return format!(
+                    "\x1B[{};{};{};{};{}T",
+                    func, start_col, start_row, first_row, last_row
+                )
+                .into();
+            }
+            DeviceControl::LinuxPalette { index, r, g, b } => {
+                return format!("\x1B]P{:X}{:02x}{:02x}{:02x}", index & 0xF, r, g, b).into();
+            }
+        };
+        Cow::Borrowed(code)
     }
-}
-
-// Optionally, add more helpers for advanced features as needed.
-
-#[cfg(test)]
-
-mod tests {
-
-    use super::*;
-
-    use crate::ansi_escape::ansi_types::*;
-
-    #[test]
-
-    fn test_format_text_bold() {
-        let creator = AnsiCreator::new();
-
-        let s = creator.format_text("hi", &[SgrAttribute::Bold]);
-
-        assert!(s.starts_with("\x1B[1m"));
-        assert!(s.ends_with("\x1B[0m"));
 
-        assert!(s.contains("hi"));
+    /// Produce the ANSI escape code to set or reset a DEC private mode (`ESC [ ? mode h/l`).
+    ///
+    /// # Arguments
+    /// * `mode` - The private mode number (e.g. 1049 for the alternate screen).
+    /// * `enabled` - Whether to set (`h`) or reset (`l`) the mode.
+    pub fn private_mode(&self, mode: u16, enabled: bool) -> String {
+        format!("\x1B[?{}{}", mode, if enabled { 'h' } else { 'l' })
     }
 
-    #[test]
-
-    fn test_format_text_fg_red() {
-        let creator = AnsiCreator::new();
-
-        // Use explicit standard SGR code for red foreground
-        let code = creator.fg_standard(31);
-        assert_eq!(code, "\x1B[31m");
-
-        let s = format!("{}hi{}", code, creator.sgr_code(SgrAttribute::Reset));
-        assert!(s.starts_with("\x1B[31m"));
-        assert!(s.ends_with("\x1B[0m"));
-        assert!(s.contains("hi"));
+    /// Enable or disable focus reporting (`ESC [ ? 1004 h` / `ESC [ ? 1004 l`).
+    ///
+    /// While enabled, the terminal sends [`AnsiEscape::Focus`] events when it gains or
+    /// loses focus; see `AnsiParseResult::focus_events`.
+    pub fn focus_reporting(&self, enable: bool) -> String {
+        self.private_mode(1004, enable)
     }
 
-    #[test]
-    fn test_sgr_reset() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::Reset), "\x1B[0m");
+    /// Produce the ANSI escape code to switch to the alternate screen buffer (`ESC [ ? 1049 h`).
+    pub fn enter_alternate_screen(&self) -> String {
+        self.private_mode(1049, true)
     }
 
-    #[test]
-    fn test_sgr_bold() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::Bold), "\x1B[1m");
+    /// Produce the ANSI escape code to leave the alternate screen buffer (`ESC [ ? 1049 l`).
+    pub fn exit_alternate_screen(&self) -> String {
+        self.private_mode(1049, false)
     }
 
-    #[test]
-    fn test_sgr_faint() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::Faint), "\x1B[2m");
+    /// Produce the device status report request for the cursor position (`ESC [ 6 n`).
+    ///
+    /// The terminal replies with `ESC [ row ; col R`, parsed as [`AnsiEscape::CursorPositionReport`].
+    pub fn request_cursor_position(&self) -> String {
+        "\x1B[6n".to_string()
     }
 
-    #[test]
-    fn test_sgr_italic() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::Italic), "\x1B[3m");
+    /// Produce the reply a terminal sends to [`AnsiCreator::request_cursor_position`]
+    /// (`ESC [ row ; col R`), e.g. to test code that handles terminal replies.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.cursor_position_report(10, 20), "\x1B[10;20R");
+    /// ```
+    pub fn cursor_position_report(&self, row: u16, col: u16) -> String {
+        format!("\x1B[{};{}R", row, col)
     }
 
-    #[test]
-    fn test_sgr_underline() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::Underline), "\x1B[4m");
+    /// Wrap `inner` in tmux's DCS passthrough (`ESC P tmux ; ... ESC \\`) so it reaches the
+    /// outer terminal, e.g. for OSC 52 clipboard or hyperlink sequences under tmux.
+    ///
+    /// Every ESC byte in `inner` is doubled, as tmux requires. tmux only forwards the
+    /// sequence when its `allow-passthrough` option is on.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(
+    ///     creator.tmux_passthrough("\x1B]52;c;aGk=\x07"),
+    ///     "\x1BPtmux;\x1B\x1B]52;c;aGk=\x07\x1B\\"
+    /// );
+    /// ```
+    pub fn tmux_passthrough(&self, inner: &str) -> String {
+        format!("\x1BPtmux;{}\x1B\\", inner.replace('\x1B', "\x1B\x1B"))
     }
 
-    #[test]
-    fn test_sgr_blink_slow() {
-        let creator = AnsiCreator::new();
-        assert_eq!(creator.sgr_code(SgrAttribute::BlinkSlow), "\x1B[5m");
+    /// Produce `fancy()` if the environment supports at least `required` colors, and
+    /// `fallback()` otherwise. Only the chosen closure is called.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, ColorLevel};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+    /// let bar = creator.conditional(
+    ///     ColorLevel::TrueColor,
+    ///     || "gradient bar".to_string(),
+    ///     || "[#####     ]".to_string(),
+    /// );
+    /// assert_eq!(bar, "[#####     ]");
+    /// ```
+    pub fn conditional<F, G>(&self, required: ColorLevel, fancy: F, fallback: G) -> String
+    where
+        F: FnOnce() -> String,
+        G: FnOnce() -> String,
+    {
+        if self.env.color_level() >= required {
+            fancy()
+        } else {
+            fallback()
+        }
+    }
+
+    /// Produce an OSC 8 hyperlink showing `text` and linking to `url`.
+    ///
+    /// When the environment does not support hyperlinks, the link is written out as
+    /// `text (url)`, or just `url` if the text is the URL itself.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_hyperlinks: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(
+    ///     creator.hyperlink("https://example.com", "site"),
+    ///     "\x1B]8;;https://example.com\x1B\\site\x1B]8;;\x1B\\"
+    /// );
+    /// ```
+    pub fn hyperlink(&self, url: &str, text: &str) -> String {
+        if self.env.supports_ansi && self.env.supports_hyperlinks {
+            format!("\x1B]8;;{}\x1B\\{}\x1B]8;;\x1B\\", url, text)
+        } else if text == url {
+            url.to_string()
+        } else {
+            format!("{} ({})", text, url)
+        }
+    }
+
+    /// Produce an OSC 52 sequence that copies `data` to the system clipboard.
+    ///
+    /// Returns an empty string when the environment does not support OSC 52, since there
+    /// is no textual fallback for setting the clipboard.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     supports_osc52: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.copy_to_clipboard("hi"), "\x1B]52;c;aGk=\x07");
+    /// ```
+    pub fn copy_to_clipboard(&self, data: &str) -> String {
+        if self.env.supports_ansi && self.env.supports_osc52 {
+            format!("\x1B]52;c;{}\x07", base64_encode(data.as_bytes()))
+        } else {
+            String::new()
+        }
+    }
+
+    /// Produce the window operation request for the text area size in characters (`ESC [ 18 t`).
+    ///
+    /// The terminal replies with `ESC [ 8 ; rows ; cols t`, parsed as [`AnsiEscape::WindowReport`];
+    /// see `AnsiParseResult::window_size`.
+    pub fn request_window_size(&self) -> String {
+        "\x1B[18t".to_string()
+    }
+
+    /// Produce the ANSI escape code for any [`AnsiEscape`] enum variant.
+    ///
+    /// # Arguments
+    /// * `code` - The escape code to convert to a string, or anything that converts into
+    ///   one (e.g. an [`SgrAttribute`] or [`CursorMove`]).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, CursorMove};
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// assert_eq!(creator.escape_code(CursorMove::Up(2)), "\x1B[2A");
+    /// ```
+    pub fn escape_code(&self, code: impl Into<AnsiEscape>) -> String {
+        let code = code.into();
+        match code {
+            AnsiEscape::Sgr(attr) => self.sgr_code(attr).into_owned(),
+            AnsiEscape::Cursor(movement) => self.cursor_code(movement),
+            AnsiEscape::Erase(erase) => self.erase_code(erase),
+            AnsiEscape::Device(device) => self.device_code(device).into_owned(),
+            AnsiEscape::Scroll(scroll) => self.scroll_code(scroll),
+            AnsiEscape::Tab(op) => self.tab_code(op),
+            AnsiEscape::CursorPositionReport { row, col } => self.cursor_position_report(row, col),
+            AnsiEscape::PrivateMode { mode, enabled } => self.private_mode(mode, enabled),
+            AnsiEscape::Mouse {
+                button,
+                col,
+                row,
+                pressed,
+            } => format!(
+                "\x1B[<{};{};{}{}",
+                button,
+                col,
+                row,
+                if pressed { 'M' } else { 'm' }
+            ),
+            AnsiEscape::Repeat(count) => format!("\x1B[{}b", count),
+            AnsiEscape::Focus { gained } => {
+                if gained {
+                    "\x1B[I".to_string()
+                } else {
+                    "\x1B[O".to_string()
+                }
+            }
+            AnsiEscape::WindowReport { kind, values } => {
+                let mut code = format!("\x1B[{}", kind);
+                for value in values {
+                    code.push_str(&format!(";{}", value));
+                }
+                code.push('t');
+                code
+            }
+            AnsiEscape::ShellIntegration(mark) => {
+                let mark = match mark {
+                    PromptMark::PromptStart => "A".to_string(),
+                    PromptMark::CommandStart => "B".to_string(),
+                    PromptMark::CommandExecuted => "C".to_string(),
+                    PromptMark::CommandFinished(Some(code)) => format!("D;{}", code),
+                    PromptMark::CommandFinished(None) => "D".to_string(),
+                };
+                format!("\x1B]133;{}\x1B\\", mark)
+            }
+            AnsiEscape::PasteStart => "\x1B[200~".to_string(),
+            AnsiEscape::PasteEnd => "\x1B[201~".to_string(),
+            AnsiEscape::FunctionKey(key) => format!("\x1B[{}~", key),
+            AnsiEscape::Unknown { params, final_byte } => {
+                format!("\x1B[{}{}", params, final_byte as char)
+            }
+        }
+    }
+}
+
+/// How per-character coloring helpers ([`AnsiCreator::gradient_text`],
+/// [`AnsiCreator::rainbow_text`]) trade output size against self-contained characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RenderBudget {
+    /// Emit a color code before every character, even if it repeats the previous one.
+    PerChar,
+    /// Emit a color code only when the color changes, coalescing runs of identical
+    /// adjacent colors. The rendered result is the same, with fewer bytes.
+    #[default]
+    Coalesce,
+}
+
+/// Horizontal alignment for [`AnsiCreator::pad_styled`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align {
+    /// Text on the left, padding on the right.
+    Left,
+    /// Padding on the left, text on the right.
+    Right,
+    /// Padding split between both sides, with the extra space (if any) on the right.
+    Center,
+}
+
+/// A tree of text with nested styling, rendered by [`AnsiCreator::format_nested`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StyledNode {
+    /// Plain text, rendered in the style of its enclosing nodes.
+    Text(String),
+    /// Child nodes rendered with these attributes added to the enclosing style.
+    Styled {
+        attrs: Vec<SgrAttribute>,
+        children: Vec<StyledNode>,
+    },
+}
+
+impl StyledNode {
+    /// Create a text node.
+    pub fn text(text: impl Into<String>) -> Self {
+        StyledNode::Text(text.into())
+    }
+
+    /// Create a styled node with the given attributes and children.
+    pub fn styled(attrs: Vec<SgrAttribute>, children: Vec<StyledNode>) -> Self {
+        StyledNode::Styled { attrs, children }
+    }
+}
+
+/// Builder for composite sequences of escape codes and text, obtained via [`AnsiCreator::sequence`].
+///
+/// Pieces are concatenated in the order they are added. Escape code pieces are
+/// omitted when the environment does not support ANSI; text is always kept.
+///
+/// SGR attributes set with no text in between are combined: setting the same kind of
+/// attribute twice (e.g. two foreground colors) keeps only the last one, while
+/// different kinds accumulate.
+#[derive(Debug, Clone)]
+pub struct Sequence<'a> {
+    creator: &'a AnsiCreator,
+    out: String,
+    pending: Vec<SgrAttribute>,
+}
+
+impl Sequence<'_> {
+    /// Move the cursor to the given row and column (1-based).
+    ///
+    /// Accepts any integer type; values that do not fit in a `u16` saturate to `u16::MAX`
+    /// instead of wrapping.
+    pub fn move_to(self, row: impl TryInto<u16>, col: impl TryInto<u16>) -> Self {
+        let row = row.try_into().unwrap_or(u16::MAX);
+        let col = col.try_into().unwrap_or(u16::MAX);
+        let code = self.creator.cursor_code(CursorMove::Position { row, col });
+        self.escape(&code)
+    }
+
+    /// Apply the given SGR attributes.
+    ///
+    /// The codes are emitted before the next piece of text or escape code.
+    pub fn sgr(mut self, attrs: &[SgrAttribute]) -> Self {
+        for attr in attrs {
+            if *attr == SgrAttribute::Reset {
+                self.pending.clear();
+            } else if attr.is_off() {
+                self.pending.retain(|a| !attr.turns_off(a) && a != attr);
+            } else {
+                self.pending.retain(|a| !a.same_kind(attr));
+            }
+            self.pending.push(*attr);
+        }
+        self
+    }
+
+    /// Append plain text.
+    pub fn text(mut self, text: &str) -> Self {
+        self = self.flush_sgr();
+        self.out.push_str(text);
+        self
+    }
+
+    /// Erase part of the display or line.
+    pub fn erase(self, erase: Erase) -> Self {
+        let code = self.creator.erase_code(erase);
+        self.escape(&code)
+    }
+
+    /// Finish the sequence and return the concatenated string.
+    pub fn build(self) -> String {
+        self.flush_sgr().out
+    }
+
+    /// Internal: append an escape code if the environment supports ANSI.
+    fn escape(mut self, code: &str) -> Self {
+        self = self.flush_sgr();
+        if self.creator.env.supports_ansi {
+            self.out.push_str(code);
+        }
+        self
+    }
+
+    /// Internal: emit the SGR attributes buffered by [`Sequence::sgr`].
+    fn flush_sgr(mut self) -> Self {
+        if self.creator.env.supports_ansi {
+            for attr in &self.pending {
+                self.out.push_str(&self.creator.sgr_code(*attr));
+            }
+        }
+        self.pending.clear();
+        self
+    }
+}
+
+/// A writer paired with an [`AnsiCreator`], obtained via [`AnsiCreator::with_writer`].
+///
+/// Each method writes its bytes immediately. Like [`Sequence`], escape codes are
+/// omitted when the environment does not support ANSI; text is always written.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct AnsiTerminal<W: std::io::Write> {
+    writer: W,
+    creator: AnsiCreator,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> AnsiTerminal<W> {
+    /// Set the foreground color.
+    pub fn set_fg(&mut self, color: Color) -> std::io::Result<()> {
+        let code = self.creator.sgr_code(SgrAttribute::Foreground(color));
+        self.escape(&code)
+    }
+
+    /// Write plain text.
+    pub fn print(&mut self, text: &str) -> std::io::Result<()> {
+        self.writer.write_all(text.as_bytes())
+    }
+
+    /// Reset all SGR attributes.
+    pub fn reset(&mut self) -> std::io::Result<()> {
+        let code = self.creator.sgr_code(SgrAttribute::Reset);
+        self.escape(&code)
+    }
+
+    /// Clear the screen and move the cursor to the top-left corner.
+    pub fn clear(&mut self) -> std::io::Result<()> {
+        let code = self.creator.erase_code(Erase::Display(EraseMode::All))
+            + &self
+                .creator
+                .cursor_code(CursorMove::Position { row: 1, col: 1 });
+        self.escape(&code)
+    }
+
+    /// Move the cursor to the given row and column (1-based).
+    pub fn move_to(&mut self, row: u16, col: u16) -> std::io::Result<()> {
+        let code = self.creator.cursor_code(CursorMove::Position { row, col });
+        self.escape(&code)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Internal: write an escape code if the environment supports ANSI.
+    fn escape(&mut self, code: &str) -> std::io::Result<()> {
+        if self.creator.env.supports_ansi {
+            self.writer.write_all(code.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Helper to linearly interpolate between two RGB colors (`t` in `0.0..=1.0`).
+fn blend_rgb(a: (u8, u8, u8), b: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    // Round half up by hand, as `f32::round` is not available without `std`
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t + 0.5) as u8;
+    (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+/// Helper to encode bytes as standard (padded) base64, as OSC 52 expects.
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Helper to convert EraseMode to its numeric code.
+fn erase_mode_num(mode: EraseMode) -> u8 {
+    match mode {
+        EraseMode::ToEnd => 0,
+        EraseMode::ToStart => 1,
+        EraseMode::All => 2,
+    }
+}
+
+// Optionally, add more helpers for advanced features as needed.
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::ansi_escape::ansi_types::*;
+
+    fn creator() -> AnsiCreator {
+        AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        })
+    }
+
+    fn color_creator() -> AnsiCreator {
+        AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_truecolor: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        })
+    }
+
+    #[test]
+
+    fn test_format_text_bold() {
+        let creator = creator();
+
+        let s = creator.format_text("hi", &[SgrAttribute::Bold]);
+
+        assert!(s.starts_with("\x1B[1m"));
+        assert!(s.ends_with("\x1B[0m"));
+
+        assert!(s.contains("hi"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_write_styled_matches_format_text() {
+        let ansi = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_truecolor: true,
+            ..AnsiEnvironment::default()
+        });
+        let no_color = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            color_disabled: true,
+            ..AnsiEnvironment::default()
+        });
+        let plain = AnsiCreator::with_environment(AnsiEnvironment::default());
+        let attrs = [
+            SgrAttribute::Bold,
+            SgrAttribute::Foreground(Color::Rgb24 { r: 1, g: 2, b: 3 }),
+        ];
+        let red = [SgrAttribute::Foreground(Color::Red)];
+        for (creator, attrs) in [(&ansi, &attrs[..]), (&no_color, &red), (&plain, &attrs)] {
+            let mut out = Vec::new();
+            creator.write_styled(&mut out, "hi", attrs).unwrap();
+            assert_eq!(out, creator.format_text("hi", attrs).into_bytes());
+        }
+        let mut out = Vec::new();
+        ansi.write_sgr(&mut out, &attrs).unwrap();
+        assert_eq!(out, b"\x1B[1m\x1B[38;2;1;2;3m");
+    }
+
+    #[test]
+
+    fn test_format_text_fg_red() {
+        let creator = creator();
+
+        // Use explicit standard SGR code for red foreground
+        let code = creator.fg_standard(31);
+        assert_eq!(code, "\x1B[31m");
+
+        let s = format!("{}hi{}", code, creator.sgr_code(SgrAttribute::Reset));
+        assert!(s.starts_with("\x1B[31m"));
+        assert!(s.ends_with("\x1B[0m"));
+        assert!(s.contains("hi"));
+    }
+
+    #[test]
+    fn test_sgr_reset() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Reset), "\x1B[0m");
+    }
+
+    #[test]
+    fn test_sgr_bold() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Bold), "\x1B[1m");
+    }
+
+    #[test]
+    fn test_sgr_faint() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Faint), "\x1B[2m");
+    }
+
+    #[test]
+    fn test_sgr_italic() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Italic), "\x1B[3m");
+    }
+
+    #[test]
+    fn test_sgr_underline() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Underline), "\x1B[4m");
+    }
+
+    #[test]
+    fn test_sgr_blink_slow() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::BlinkSlow), "\x1B[5m");
     }
 
     #[test]
     fn test_sgr_blink_rapid() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
 
         assert_eq!(creator.sgr_code(SgrAttribute::BlinkRapid), "\x1B[6m");
+        assert_eq!(creator.sgr_code(SgrAttribute::NotBlinking), "\x1B[25m");
     }
 
     #[test]
     fn test_sgr_reverse() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.sgr_code(SgrAttribute::Reverse), "\x1B[7m");
     }
 
     #[test]
     fn test_sgr_conceal() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.sgr_code(SgrAttribute::Conceal), "\x1B[8m");
     }
 
     #[test]
     fn test_sgr_crossed_out() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.sgr_code(SgrAttribute::CrossedOut), "\x1B[9m");
     }
 
+    #[test]
+    fn test_sgr_overline() {
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Overline), "\x1B[53m");
+        assert_eq!(creator.sgr_code(SgrAttribute::NotOverline), "\x1B[55m");
+        assert_eq!(creator.sgr_code(SgrAttribute::Framed), "\x1B[51m");
+        assert_eq!(creator.sgr_code(SgrAttribute::Encircled), "\x1B[52m");
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::NotFramedEncircled),
+            "\x1B[54m"
+        );
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::DefaultForeground),
+            "\x1B[39m"
+        );
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::DefaultBackground),
+            "\x1B[49m"
+        );
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::DefaultUnderlineColor),
+            "\x1B[59m"
+        );
+    }
+
     #[test]
     fn test_sgr_fg_standard_colors() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.fg_standard(30), "\x1B[30m");
         assert_eq!(creator.fg_standard(31), "\x1B[31m");
         assert_eq!(creator.fg_standard(32), "\x1B[32m");
@@ -440,7 +1833,7 @@ mod tests {
 
     #[test]
     fn test_sgr_fg_bright_colors() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.fg_standard(90), "\x1B[90m");
         assert_eq!(creator.fg_standard(91), "\x1B[91m");
         assert_eq!(creator.fg_standard(92), "\x1B[92m");
@@ -453,7 +1846,7 @@ mod tests {
 
     #[test]
     fn test_sgr_bg_standard_colors() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.bg_standard(40), "\x1B[40m");
         assert_eq!(creator.bg_standard(41), "\x1B[41m");
         assert_eq!(creator.bg_standard(42), "\x1B[42m");
@@ -466,7 +1859,7 @@ mod tests {
 
     #[test]
     fn test_sgr_bg_bright_colors() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.bg_standard(100), "\x1B[100m");
         assert_eq!(creator.bg_standard(101), "\x1B[101m");
         assert_eq!(creator.bg_standard(102), "\x1B[102m");
@@ -479,67 +1872,67 @@ mod tests {
 
     #[test]
     fn test_sgr_fg_8bit_color() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.fg_8bit(123), "\x1B[38;5;123m");
     }
 
     #[test]
     fn test_sgr_fg_24bit_color() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.fg_24bit(10, 20, 30), "\x1B[38;2;10;20;30m");
     }
 
     #[test]
     fn test_sgr_underline_color_8bit() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.underline_8bit(42), "\x1B[58;5;42m");
     }
 
     #[test]
     fn test_sgr_underline_color_24bit() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.underline_24bit(1, 2, 3), "\x1B[58;2;1;2;3m");
     }
 
     #[test]
     fn test_cursor_up() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::Up(3)), "\x1B[3A");
     }
 
     #[test]
     fn test_cursor_down() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::Down(2)), "\x1B[2B");
     }
 
     #[test]
     fn test_cursor_forward() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::Forward(5)), "\x1B[5C");
     }
 
     #[test]
     fn test_cursor_backward() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::Backward(4)), "\x1B[4D");
     }
 
     #[test]
     fn test_cursor_next_line() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::NextLine(1)), "\x1B[1E");
     }
 
     #[test]
     fn test_cursor_previous_line() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.cursor_code(CursorMove::PreviousLine(2)), "\x1B[2F");
     }
 
     #[test]
     fn test_cursor_horizontal_absolute() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.cursor_code(CursorMove::HorizontalAbsolute(7)),
             "\x1B[7G"
@@ -548,7 +1941,7 @@ mod tests {
 
     #[test]
     fn test_cursor_position() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.cursor_code(CursorMove::Position { row: 3, col: 4 }),
             "\x1B[3;4H"
@@ -557,7 +1950,7 @@ mod tests {
 
     #[test]
     fn test_erase_display_to_end() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.erase_code(Erase::Display(EraseMode::ToEnd)),
             "\x1B[0J"
@@ -566,7 +1959,7 @@ mod tests {
 
     #[test]
     fn test_erase_display_to_start() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.erase_code(Erase::Display(EraseMode::ToStart)),
             "\x1B[1J"
@@ -575,7 +1968,7 @@ mod tests {
 
     #[test]
     fn test_erase_display_all() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.erase_code(Erase::Display(EraseMode::All)),
             "\x1B[2J"
@@ -584,13 +1977,13 @@ mod tests {
 
     #[test]
     fn test_erase_line_to_end() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.erase_code(Erase::Line(EraseMode::ToEnd)), "\x1B[0K");
     }
 
     #[test]
     fn test_erase_line_to_start() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(
             creator.erase_code(Erase::Line(EraseMode::ToStart)),
             "\x1B[1K"
@@ -599,31 +1992,937 @@ mod tests {
 
     #[test]
     fn test_erase_line_all() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.erase_code(Erase::Line(EraseMode::All)), "\x1B[2K");
     }
 
     #[test]
     fn test_device_save_cursor() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.device_code(DeviceControl::SaveCursor), "\x1B[s");
     }
 
     #[test]
     fn test_device_restore_cursor() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.device_code(DeviceControl::RestoreCursor), "\x1B[u");
     }
 
     #[test]
     fn test_device_hide_cursor() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.device_code(DeviceControl::HideCursor), "\x1B[?25l");
     }
 
     #[test]
     fn test_device_show_cursor() {
-        let creator = AnsiCreator::new();
+        let creator = creator();
         assert_eq!(creator.device_code(DeviceControl::ShowCursor), "\x1B[?25h");
     }
+
+    #[test]
+    fn test_heatmap_endpoints_and_midpoint() {
+        let creator = color_creator();
+        assert_eq!(
+            creator.heatmap(0.0, 0.0, 100.0),
+            Color::Rgb24 { r: 0, g: 0, b: 255 }
+        );
+        assert_eq!(
+            creator.heatmap(100.0, 0.0, 100.0),
+            Color::Rgb24 { r: 255, g: 0, b: 0 }
+        );
+        assert_eq!(
+            creator.heatmap(50.0, 0.0, 100.0),
+            Color::Rgb24 { r: 0, g: 255, b: 0 }
+        );
+        assert_eq!(
+            creator.heatmap(25.0, 0.0, 100.0),
+            Color::Rgb24 {
+                r: 0,
+                g: 128,
+                b: 128
+            }
+        );
+        // Out-of-range values are clamped
+        assert_eq!(
+            creator.heatmap(-5.0, 0.0, 100.0),
+            creator.heatmap(0.0, 0.0, 100.0)
+        );
+        assert_eq!(
+            creator.heatmap(500.0, 0.0, 100.0),
+            creator.heatmap(100.0, 0.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn test_heatmap_downgraded_and_cell() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        assert_eq!(creator.heatmap(1.0, 0.0, 1.0), Color::AnsiValue(196));
+        assert_eq!(
+            creator.heatmap_cell(1.0, 0.0, 1.0, "hot"),
+            "\x1B[48;5;196mhot\x1B[0m"
+        );
+    }
+
+    #[test]
+    fn test_with_environment_downgrades_rgb24_to_8bit() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::Foreground(Color::Rgb24 {
+                r: 255,
+                g: 0,
+                b: 0
+            })),
+            "\x1B[38;5;196m"
+        );
+    }
+
+    #[test]
+    fn test_with_environment_downgrades_rgb24_to_basic16() {
+        let creator = creator();
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::Foreground(Color::Rgb24 {
+                r: 250,
+                g: 5,
+                b: 5
+            })),
+            "\x1B[91m"
+        );
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::Background(Color::AnsiValue(21))),
+            "\x1B[44m"
+        );
+    }
+
+    #[test]
+    fn test_tab_code() {
+        let creator = creator();
+        assert_eq!(creator.tab_code(TabOp::Set), "\x1BH");
+        assert_eq!(creator.tab_code(TabOp::Forward(2)), "\x1B[2I");
+        assert_eq!(creator.tab_code(TabOp::Backward(1)), "\x1B[1Z");
+        assert_eq!(creator.tab_code(TabOp::Clear(TabClear::Current)), "\x1B[0g");
+        assert_eq!(creator.tab_code(TabOp::Clear(TabClear::All)), "\x1B[3g");
+    }
+
+    #[test]
+    fn test_device_linux_palette() {
+        let creator = creator();
+        assert_eq!(
+            creator.device_code(DeviceControl::LinuxPalette {
+                index: 10,
+                r: 255,
+                g: 0,
+                b: 16
+            }),
+            "\x1B]PAff0010"
+        );
+        assert_eq!(
+            creator.device_code(DeviceControl::LinuxPaletteReset),
+            "\x1B]R"
+        );
+    }
+
+    #[test]
+    fn test_device_cursor_blink() {
+        let creator = creator();
+        assert_eq!(
+            creator.device_code(DeviceControl::SetCursorBlink(true)),
+            "\x1B[?12h"
+        );
+        assert_eq!(
+            creator.device_code(DeviceControl::SetCursorBlink(false)),
+            "\x1B[?12l"
+        );
+    }
+
+    #[test]
+    fn test_private_mode() {
+        let creator = creator();
+        assert_eq!(creator.private_mode(1049, true), "\x1B[?1049h");
+        assert_eq!(creator.private_mode(2004, false), "\x1B[?2004l");
+    }
+
+    #[test]
+    fn test_alternate_screen() {
+        let creator = creator();
+        assert_eq!(creator.enter_alternate_screen(), "\x1B[?1049h");
+        assert_eq!(creator.exit_alternate_screen(), "\x1B[?1049l");
+        assert_eq!(
+            creator.device_code(DeviceControl::EnterAltScreen),
+            "\x1B[?1049h"
+        );
+        assert_eq!(
+            creator.device_code(DeviceControl::ExitAltScreen),
+            "\x1B[?1049l"
+        );
+    }
+
+    #[test]
+    fn test_device_kitty_keyboard() {
+        let creator = creator();
+        assert_eq!(
+            creator.device_code(DeviceControl::SetKittyKeyboard(
+                KittyKeyboardFlags::from_bits(5)
+            )),
+            "\x1B[>5u"
+        );
+        assert_eq!(
+            creator.device_code(DeviceControl::PopKittyKeyboard(1)),
+            "\x1B[<1u"
+        );
+    }
+
+    #[test]
+    fn test_device_cursor_style_round_trip() {
+        let creator = creator();
+        let code = creator.device_code(DeviceControl::SetCursorStyle(CursorStyle::SteadyBar));
+        assert_eq!(code, "\x1B[6 q");
+        assert_eq!(
+            parse_ansi_annotated(&code).final_cursor_style(),
+            Some(CursorStyle::SteadyBar)
+        );
+    }
+
+    #[test]
+    fn test_measure_ignores_attributes() {
+        let creator = creator();
+        assert_eq!(creator.measure("\u{65E5}\u{672C}", &[]), 4);
+        assert_eq!(
+            creator.measure(
+                "\u{65E5}\u{672C}",
+                &[SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)]
+            ),
+            4
+        );
+        assert_eq!(creator.measure("abc", &[SgrAttribute::Underline]), 3);
+    }
+
+    #[test]
+    fn test_format_nested_inherits_and_restores() {
+        let creator = creator();
+        let tree = StyledNode::styled(
+            vec![SgrAttribute::Bold],
+            vec![
+                StyledNode::text("a"),
+                StyledNode::styled(
+                    vec![SgrAttribute::Foreground(Color::Red)],
+                    vec![StyledNode::text("b")],
+                ),
+                StyledNode::text("c"),
+            ],
+        );
+        assert_eq!(
+            creator.format_nested(&tree),
+            "\x1B[1ma\x1B[31mb\x1B[0m\x1B[1mc\x1B[0m"
+        );
+    }
+
+    #[test]
+    fn test_format_nested_unstyled_parent() {
+        let creator = creator();
+        let tree = StyledNode::styled(
+            vec![],
+            vec![
+                StyledNode::styled(vec![SgrAttribute::Italic], vec![StyledNode::text("a")]),
+                StyledNode::text("b"),
+            ],
+        );
+        assert_eq!(creator.format_nested(&tree), "\x1B[3ma\x1B[0mb");
+    }
+
+    #[test]
+    fn test_format_nested_plain_text() {
+        let creator = creator();
+        assert_eq!(creator.format_nested(&StyledNode::text("plain")), "plain");
+    }
+
+    #[test]
+    fn test_request_cursor_position() {
+        let creator = creator();
+        assert_eq!(creator.request_cursor_position(), "\x1B[6n");
+    }
+
+    #[test]
+    fn test_cursor_position_report_round_trip() {
+        let creator = creator();
+        let report = creator.cursor_position_report(10, 20);
+        let result = parse_ansi_annotated(&report);
+        assert_eq!(result.text, "");
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::CursorPositionReport { row: 10, col: 20 }
+        );
+    }
+
+    #[test]
+    fn test_compact_reset() {
+        use crate::ansi_escape::ansi_interpreter::{AnsiParser, Event};
+
+        let creator = creator();
+        assert_eq!(creator.sgr_code(SgrAttribute::Reset), "\x1B[0m");
+        let creator = creator.with_compact_reset(true);
+        let code = creator.sgr_code(SgrAttribute::Reset);
+        assert_eq!(code, "\x1B[m");
+        let events: Vec<Event> = AnsiParser::new(&code).events().collect();
+        assert_eq!(
+            events,
+            vec![Event::Escape(AnsiEscape::Sgr(SgrAttribute::Reset))]
+        );
+    }
+
+    #[test]
+    fn test_truncate_styled_colored_cjk() {
+        let creator = color_creator();
+        let input = "\x1B[31m\u{65E5}\u{672C}\u{8A9E}\x1B[0m!";
+        // Each CJK character is two columns wide
+        assert_eq!(
+            creator.truncate_styled(input, 4),
+            "\x1B[31m\u{65E5}\u{672C}\x1B[0m"
+        );
+        // The third character would straddle column 5, so it is dropped
+        assert_eq!(
+            creator.truncate_styled(input, 5),
+            "\x1B[31m\u{65E5}\u{672C}\x1B[0m"
+        );
+        // Everything fits: the original reset is kept and no extra one is added
+        assert_eq!(creator.truncate_styled(input, 7), input);
+    }
+
+    #[test]
+    fn test_truncate_styled_resets_open_style() {
+        let creator = color_creator();
+        assert_eq!(
+            creator.truncate_styled("ab\x1B[1;32mcdef", 3),
+            "ab\x1B[1m\x1B[32mc\x1B[0m"
+        );
+        assert_eq!(creator.truncate_styled("abc\x1B[2Jdef", 3), "abc\x1B[2J");
+        assert_eq!(creator.truncate_styled("\x1B[1mabc", 0), "\x1B[1m\x1B[0m");
+    }
+
+    #[test]
+    fn test_truncate_styled_keeps_hyperlinks() {
+        let creator = creator();
+        let input = "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x1B\\ text";
+        // The cut link is closed
+        assert_eq!(
+            creator.truncate_styled(input, 2),
+            "\x1B]8;;https://example.com\x1B\\li\x1B]8;;\x1B\\"
+        );
+        // The link's own close is kept and no extra one is added
+        assert_eq!(
+            creator.truncate_styled(input, 6),
+            "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x1B\\ t"
+        );
+        assert_eq!(creator.truncate_styled(input, 9), input);
+        // Other strings are copied as they are
+        assert_eq!(
+            creator.truncate_styled("\x1B]0;title\x07abc\x1BPq#0\x1B\\def", 5),
+            "\x1B]0;title\x07abc\x1BPq#0\x1B\\de"
+        );
+    }
+
+    #[test]
+    fn test_pad_styled_equal_widths() {
+        use crate::ansi_escape::ansi_interpreter::parse_ansi_annotated;
+
+        let creator = creator();
+        let a = creator.format_text("red", &[SgrAttribute::Foreground(Color::Red)]);
+        let b = creator.format_text(
+            "\u{65E5}\u{672C}",
+            &[SgrAttribute::Bold, SgrAttribute::Background(Color::Blue)],
+        );
+        for align in [Align::Left, Align::Right, Align::Center] {
+            let pa = creator.pad_styled(&a, 10, align);
+            let pb = creator.pad_styled(&b, 10, align);
+            assert_eq!(parse_ansi_annotated(&pa).display_width(), 10);
+            assert_eq!(parse_ansi_annotated(&pb).display_width(), 10);
+        }
+    }
+
+    #[test]
+    fn test_pad_styled_padding_outside_style() {
+        let creator = creator();
+        assert_eq!(
+            creator.pad_styled("\x1B[31mab\x1B[0m", 5, Align::Left),
+            "\x1B[31mab\x1B[0m   "
+        );
+        assert_eq!(
+            creator.pad_styled("\x1B[31mabc", 6, Align::Center),
+            " \x1B[31mabc\x1B[0m  "
+        );
+        assert_eq!(creator.pad_styled("abcdef", 3, Align::Right), "abcdef");
+    }
+
+    #[test]
+    fn test_gradient_text_identical_endpoints_coalesce() {
+        let creator = color_creator();
+        let green = Color::Rgb24 { r: 0, g: 200, b: 0 };
+        let s = creator.gradient_text("hello", green, green, RenderBudget::Coalesce);
+        assert_eq!(s, "\x1B[38;2;0;200;0mhello\x1B[0m");
+        let s = creator.gradient_text("hello", green, green, RenderBudget::PerChar);
+        assert_eq!(s.matches("\x1B[38;2;0;200;0m").count(), 5);
+    }
+
+    #[test]
+    fn test_gradient_text_coalesces_after_downgrade() {
+        let creator = creator();
+        // Nearby shades all downgrade to the same basic color
+        let from = Color::Rgb24 { r: 200, g: 0, b: 0 };
+        let to = Color::Rgb24 {
+            r: 210,
+            g: 10,
+            b: 0,
+        };
+        let s = creator.gradient_text("abcdef", from, to, RenderBudget::Coalesce);
+        assert_eq!(s, "\x1B[31mabcdef\x1B[0m");
+        let parsed = parse_ansi_annotated(&s);
+        let per_char = creator.gradient_text("abcdef", from, to, RenderBudget::PerChar);
+        assert_eq!(parse_ansi_annotated(&per_char).spans, parsed.spans);
+    }
+
+    #[test]
+    fn test_gradient_and_rainbow_without_color() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        let s = creator.gradient_text("plain", Color::Red, Color::Blue, RenderBudget::Coalesce);
+        assert_eq!(s, "plain");
+        assert_eq!(
+            creator.rainbow_text("plain", 3, RenderBudget::PerChar),
+            "plain"
+        );
+    }
+
+    #[test]
+    fn test_rainbow_text_cycles() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_truecolor: true,
+            ..AnsiEnvironment::default()
+        });
+        let s = creator.rainbow_text("abab", 2, RenderBudget::Coalesce);
+        let spans = parse_ansi_annotated(&s).spans;
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].codes, spans[2].codes);
+        assert_ne!(spans[0].codes, spans[1].codes);
+    }
+
+    #[test]
+    fn test_tmux_passthrough_doubles_esc() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        let inner = "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x1B\\";
+        let wrapped = creator.tmux_passthrough(inner);
+        assert!(wrapped.starts_with("\x1BPtmux;"));
+        assert!(wrapped.ends_with("\x1B\\"));
+        let body = &wrapped["\x1BPtmux;".len()..wrapped.len() - 2];
+        assert_eq!(
+            body.matches('\x1B').count(),
+            2 * inner.matches('\x1B').count()
+        );
+        assert_eq!(body.replace("\x1B\x1B", "\x1B"), inner);
+    }
+
+    #[test]
+    fn test_static_codes_are_borrowed() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        for attr in [
+            SgrAttribute::Reset,
+            SgrAttribute::Bold,
+            SgrAttribute::Overline,
+            SgrAttribute::DefaultForeground,
+        ] {
+            assert!(matches!(creator.sgr_code(attr), Cow::Borrowed(_)));
+        }
+        assert!(matches!(
+            creator
+                .with_compact_reset(true)
+                .sgr_code(SgrAttribute::Reset),
+            Cow::Borrowed(SGR_RESET_COMPACT)
+        ));
+        assert!(matches!(
+            creator.device_code(DeviceControl::HideCursor),
+            Cow::Borrowed(HIDE_CURSOR)
+        ));
+        assert!(matches!(
+            creator.sgr_code(SgrAttribute::Foreground(Color::AnsiValue(21))),
+            Cow::Owned(_)
+        ));
+        assert!(matches!(
+            creator.device_code(DeviceControl::SetKittyKeyboard(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+            )),
+            Cow::Owned(_)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_terminal_writes_red_text_and_reset() {
+        let env = AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        };
+        let mut term = AnsiCreator::with_environment(env).with_writer(Vec::new());
+        term.set_fg(Color::Red).unwrap();
+        term.print("error").unwrap();
+        term.reset().unwrap();
+        let out = String::from_utf8(term.into_inner()).unwrap();
+        assert_eq!(out, "\x1B[31merror\x1B[0m");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_terminal_clear_and_move_to() {
+        let env = AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        };
+        let mut term = AnsiCreator::with_environment(env).with_writer(Vec::new());
+        term.clear().unwrap();
+        term.move_to(3, 7).unwrap();
+        assert_eq!(term.into_inner(), b"\x1B[2J\x1B[1;1H\x1B[3;7H");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_terminal_without_ansi_writes_text_only() {
+        let mut term =
+            AnsiCreator::with_environment(AnsiEnvironment::default()).with_writer(Vec::new());
+        term.set_fg(Color::Red).unwrap();
+        term.print("plain").unwrap();
+        term.reset().unwrap();
+        assert_eq!(term.into_inner(), b"plain");
+    }
+
+    #[test]
+    fn test_sequence_move_to_saturates() {
+        let creator = color_creator();
+        assert_eq!(
+            creator.sequence().move_to(70000, 1).build(),
+            "\x1B[65535;1H"
+        );
+        assert_eq!(
+            creator.sequence().move_to(3usize, 70_000u32).build(),
+            "\x1B[3;65535H"
+        );
+        assert_eq!(creator.sequence().move_to(4u16, 5u16).build(), "\x1B[4;5H");
+    }
+
+    #[test]
+    fn test_focus_reporting() {
+        let creator = creator();
+        assert_eq!(creator.focus_reporting(true), "\x1B[?1004h");
+        assert_eq!(creator.focus_reporting(false), "\x1B[?1004l");
+        assert_eq!(
+            creator.escape_code(AnsiEscape::Focus { gained: false }),
+            "\x1B[O"
+        );
+    }
+
+    #[test]
+    fn test_request_window_size() {
+        let creator = creator();
+        assert_eq!(creator.request_window_size(), "\x1B[18t");
+        assert_eq!(
+            creator.escape_code(AnsiEscape::WindowReport {
+                kind: 8,
+                values: vec![24, 80]
+            }),
+            "\x1B[8;24;80t"
+        );
+    }
+
+    #[test]
+    fn test_sequence_move_color_text_reset() {
+        let creator = color_creator();
+        let s = creator
+            .sequence()
+            .move_to(2, 5)
+            .sgr(&[SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)])
+            .text("hi")
+            .sgr(&[SgrAttribute::Reset])
+            .erase(Erase::Line(EraseMode::ToEnd))
+            .build();
+        assert_eq!(s, "\x1B[2;5H\x1B[1m\x1B[31mhi\x1B[0m\x1B[0K");
+    }
+
+    #[test]
+    fn test_sequence_same_slot_keeps_last() {
+        let creator = color_creator();
+        let s = creator
+            .sequence()
+            .sgr(&[SgrAttribute::Foreground(Color::Red)])
+            .sgr(&[SgrAttribute::Foreground(Color::Blue)])
+            .build();
+        assert_eq!(s, "\x1B[34m");
+        // Different slots accumulate
+        let s = creator
+            .sequence()
+            .sgr(&[SgrAttribute::Foreground(Color::Red)])
+            .sgr(&[SgrAttribute::Bold])
+            .text("x")
+            .build();
+        assert_eq!(s, "\x1B[31m\x1B[1mx");
+        // Text in between keeps both codes
+        let s = creator
+            .sequence()
+            .sgr(&[SgrAttribute::Foreground(Color::Red)])
+            .text("a")
+            .sgr(&[SgrAttribute::Foreground(Color::Blue)])
+            .text("b")
+            .build();
+        assert_eq!(s, "\x1B[31ma\x1B[34mb");
+        // A reset drops everything buffered before it
+        let s = creator
+            .sequence()
+            .sgr(&[
+                SgrAttribute::Bold,
+                SgrAttribute::Reset,
+                SgrAttribute::Italic,
+            ])
+            .build();
+        assert_eq!(s, "\x1B[0m\x1B[3m");
+    }
+
+    #[test]
+    fn test_sequence_without_ansi_keeps_text_only() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        let s = creator
+            .sequence()
+            .move_to(2, 5)
+            .sgr(&[SgrAttribute::Bold])
+            .text("hi")
+            .sgr(&[SgrAttribute::Reset])
+            .build();
+        assert_eq!(s, "hi");
+    }
+
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn test_fg_terminfo_uses_setaf() {
+        let mut builder = terminfo::Database::new();
+        builder.name("mock");
+        builder.raw("colors", 256);
+        builder.raw("setaf", "\x1B[38;5;%p1%dm");
+        let db = builder.build().unwrap();
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        assert_eq!(creator.fg_terminfo_with(&db, Color::Red), "\x1B[38;5;1m");
+    }
+
+    #[cfg(feature = "terminfo")]
+    #[test]
+    fn test_fg_terminfo_falls_back_without_setaf() {
+        let mut builder = terminfo::Database::new();
+        builder.name("mock");
+        let db = builder.build().unwrap();
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        assert_eq!(creator.fg_terminfo_with(&db, Color::Red), "\x1B[31m");
+    }
+
+    #[test]
+    fn test_detect_honors_no_color() {
+        let env = AnsiEnvironment::detect_with(
+            true,
+            vars(&[("TERM", "xterm-256color"), ("NO_COLOR", "1")]),
+        );
+        assert!(env.color_disabled);
+        assert!(!env.supports_truecolor);
+        assert!(!env.supports_8bit_color);
+
+        // An empty NO_COLOR does not disable color
+        let env = AnsiEnvironment::detect_with(
+            true,
+            vars(&[("TERM", "xterm-256color"), ("NO_COLOR", "")]),
+        );
+        assert!(!env.color_disabled);
+        assert!(env.supports_8bit_color);
+    }
+
+    /// Builds an environment lookup from a fixed list of variables.
+    fn vars(list: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            list.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_from_parts_dumb_terminal() {
+        let env = AnsiEnvironment::from_parts(true, Some("dumb"), None, false, None);
+        assert!(!env.supports_ansi);
+        assert!(!env.supports_truecolor);
+    }
+
+    #[test]
+    fn test_environment_default_is_all_false() {
+        let env = AnsiEnvironment::default();
+        assert_eq!(
+            env,
+            AnsiEnvironment {
+                supports_ansi: false,
+                supports_truecolor: false,
+                supports_8bit_color: false,
+                color_disabled: false,
+                supports_hyperlinks: false,
+                supports_osc52: false,
+                cols: None,
+                rows: None,
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_creator_default_detects_environment() {
+        let creator = AnsiCreator::default();
+        assert_eq!(creator.env, AnsiEnvironment::detect());
+    }
+
+    #[test]
+    fn test_from_parts_256color() {
+        let env = AnsiEnvironment::from_parts(true, Some("xterm-256color"), None, false, None);
+        assert!(env.supports_ansi);
+        assert!(env.supports_8bit_color);
+        assert!(!env.supports_truecolor);
+    }
+
+    #[test]
+    fn test_from_parts_truecolor_via_colorterm() {
+        let env = AnsiEnvironment::from_parts(
+            true,
+            Some("xterm-256color"),
+            Some("truecolor"),
+            false,
+            None,
+        );
+        assert!(env.supports_truecolor);
+        assert!(env.supports_8bit_color);
+        let env =
+            AnsiEnvironment::from_parts(true, Some("screen-256color"), Some("24bit"), false, None);
+        assert!(env.supports_truecolor);
+    }
+
+    #[test]
+    fn test_from_parts_windows_terminal_and_vscode() {
+        let env = AnsiEnvironment::from_parts(true, None, None, true, None);
+        assert!(env.supports_truecolor);
+        let env = AnsiEnvironment::from_parts(true, None, None, false, Some("vscode"));
+        assert!(env.supports_truecolor);
+    }
+
+    #[test]
+    fn test_from_parts_not_a_tty() {
+        let env = AnsiEnvironment::from_parts(false, Some("xterm-256color"), None, false, None);
+        assert!(!env.supports_ansi);
+        assert!(!env.color_disabled);
+    }
+
+    #[test]
+    fn test_detect_tty_default() {
+        let env = AnsiEnvironment::detect_with(true, vars(&[("TERM", "xterm-256color")]));
+        assert!(env.supports_ansi);
+        assert!(!env.color_disabled);
+        let env = AnsiEnvironment::detect_with(false, vars(&[("TERM", "xterm-256color")]));
+        assert!(!env.supports_ansi);
+    }
+
+    #[test]
+    fn test_detect_size_from_env() {
+        let env = AnsiEnvironment::detect_with(false, vars(&[("COLUMNS", "80"), ("LINES", "24")]));
+        assert_eq!((env.cols, env.rows), (Some(80), Some(24)));
+        let env = AnsiEnvironment::detect_with(false, vars(&[("COLUMNS", "wide"), ("LINES", "0")]));
+        assert_eq!((env.cols, env.rows), (None, None));
+    }
+
+    #[test]
+    fn test_detect_hyperlinks_and_osc52() {
+        let detect = |list| AnsiEnvironment::detect_with(true, vars(list));
+        let env = detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]);
+        assert!(env.supports_hyperlinks && env.supports_osc52);
+        let env = detect(&[
+            ("TERM", "xterm-256color"),
+            ("TERM_PROGRAM", "iTerm.app"),
+            ("TERM_PROGRAM_VERSION", "3.4.19"),
+        ]);
+        assert!(env.supports_hyperlinks && env.supports_osc52);
+        let env = detect(&[
+            ("TERM", "xterm-256color"),
+            ("TERM_PROGRAM", "iTerm.app"),
+            ("TERM_PROGRAM_VERSION", "2.9"),
+        ]);
+        assert!(!env.supports_hyperlinks && !env.supports_osc52);
+        let env = detect(&[("TERM", "xterm-256color"), ("VTE_VERSION", "7200")]);
+        assert!(env.supports_hyperlinks && !env.supports_osc52);
+        let env = detect(&[("TERM", "xterm-256color")]);
+        assert!(!env.supports_hyperlinks && !env.supports_osc52);
+        // Not a tty: no ANSI, so no OSC features either
+        let env = AnsiEnvironment::detect_with(false, vars(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(!env.supports_hyperlinks && !env.supports_osc52);
+    }
+
+    #[test]
+    fn test_conditional_picks_by_color_level() {
+        let truecolor = color_creator();
+        let basic = creator();
+        let pick = |creator: &AnsiCreator| {
+            creator.conditional(
+                ColorLevel::TrueColor,
+                || "fancy".to_string(),
+                || "fallback".to_string(),
+            )
+        };
+        assert_eq!(pick(&truecolor), "fancy");
+        assert_eq!(pick(&basic), "fallback");
+        assert_eq!(basic.env.color_level(), ColorLevel::Basic16);
+        let disabled = AnsiEnvironment {
+            color_disabled: true,
+            ..truecolor.env
+        };
+        assert_eq!(disabled.color_level(), ColorLevel::None);
+    }
+
+    #[test]
+    fn test_hyperlink_degrades_without_support() {
+        let env = AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        };
+        let creator = AnsiCreator::with_environment(env);
+        assert_eq!(
+            creator.hyperlink("https://example.com", "docs"),
+            "docs (https://example.com)"
+        );
+        assert_eq!(
+            creator.hyperlink("https://example.com", "https://example.com"),
+            "https://example.com"
+        );
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_hyperlinks: true,
+            ..env
+        });
+        assert!(
+            creator
+                .hyperlink("https://example.com", "docs")
+                .starts_with("\x1B]8;;https://example.com\x1B\\docs")
+        );
+    }
+
+    #[test]
+    fn test_copy_to_clipboard_degrades_without_support() {
+        let env = AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        };
+        assert_eq!(
+            AnsiCreator::with_environment(env).copy_to_clipboard("x"),
+            ""
+        );
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_osc52: true,
+            ..env
+        });
+        assert_eq!(creator.copy_to_clipboard("x"), "\x1B]52;c;eA==\x07");
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_detect_clicolor_force() {
+        let env = AnsiEnvironment::detect_with(false, vars(&[("CLICOLOR_FORCE", "1")]));
+        assert!(env.supports_ansi);
+        let env = AnsiEnvironment::detect_with(false, vars(&[("CLICOLOR_FORCE", "0")]));
+        assert!(!env.supports_ansi);
+        let env = AnsiEnvironment::detect_with(false, vars(&[("CLICOLOR_FORCE", "")]));
+        assert!(!env.supports_ansi);
+    }
+
+    #[test]
+    fn test_detect_no_color_beats_clicolor_force() {
+        let env = AnsiEnvironment::detect_with(
+            false,
+            vars(&[
+                ("TERM", "xterm-256color"),
+                ("COLORTERM", "truecolor"),
+                ("CLICOLOR_FORCE", "1"),
+                ("NO_COLOR", "1"),
+            ]),
+        );
+        assert!(env.supports_ansi);
+        assert!(env.color_disabled);
+        assert!(!env.supports_truecolor);
+        assert!(!env.supports_8bit_color);
+    }
+
+    #[test]
+    fn test_format_text_color_disabled() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            color_disabled: true,
+            ..AnsiEnvironment::default()
+        });
+        let s = creator.format_text(
+            "hi",
+            &[
+                SgrAttribute::Bold,
+                SgrAttribute::Foreground(Color::Red),
+                SgrAttribute::Background(Color::Rgb24 { r: 1, g: 2, b: 3 }),
+            ],
+        );
+        assert_eq!(s, "\x1B[1mhi\x1B[0m");
+    }
+
+    #[test]
+    fn test_format_text_no_stray_reset() {
+        let plain = AnsiCreator::with_environment(AnsiEnvironment::default());
+        assert!(!plain.env.supports_ansi);
+        assert_eq!(
+            plain.format_text("hi", &[SgrAttribute::Bold, SgrAttribute::Italic]),
+            "hi"
+        );
+        let no_color = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            color_disabled: true,
+            ..AnsiEnvironment::default()
+        });
+        assert_eq!(
+            no_color.format_text("hi", &[SgrAttribute::Foreground(Color::Red)]),
+            "hi"
+        );
+        assert_eq!(no_color.format_text("hi", &[]), "hi");
+    }
+
+    #[test]
+    fn test_with_environment_keeps_rgb24_with_truecolor() {
+        let creator = color_creator();
+        assert_eq!(
+            creator.sgr_code(SgrAttribute::Foreground(Color::Rgb24 { r: 1, g: 2, b: 3 })),
+            "\x1B[38;2;1;2;3m"
+        );
+    }
 }
@@ -4,28 +4,83 @@
 //! This module will parse a string containing ANSI escape codes and produce
 //! enums/objects describing the codes for downstream consumption.
 
+use super::ansi_creator::AnsiCreator;
 use super::ansi_types::{
-    AnsiEscape, Color, CursorMove, DeviceControl, Erase, EraseMode, SgrAttribute,
+    AltScreenMode, AnsiEscape, Color, CursorMove, CursorStyle, DeviceControl, Erase, EraseMode,
+    Key, KittyKeyboardFlags, Modifiers, PromptMark, Scroll, SgrAttribute, TabClear, TabOp,
+    UnderlineKind,
 };
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::ops::Range;
+use memchr::{memchr, memchr2};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Represents a span of text affected by an ANSI code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// Represents a span of text affected by an ANSI escape code.
 /// Used to annotate which range of the cleaned text is affected by a particular code.
 pub struct AnsiSpan {
-    /// Byte offset in the cleaned text where the span starts.
+    /// Offset in the cleaned text where the span starts (bytes, or chars in char-offset mode).
     pub start: usize,
-    /// Byte offset (exclusive) where the span ends.
+    /// Offset (exclusive) where the span ends.
     pub end: usize,
     /// The set of SGR attributes affecting this span.
     pub codes: Vec<SgrAttribute>,
 }
 
+impl AnsiSpan {
+    /// The foreground and background colors the span is displayed with, as
+    /// `(foreground, background)`. `None` means the terminal's default color.
+    ///
+    /// Colors are taken from the codes in order (so `DefaultForeground` after a
+    /// `Foreground` clears it), then `Reverse` swaps them, then `Conceal` sets the
+    /// foreground to the background so the text is hidden. Concealed text on the default
+    /// background therefore has no foreground color either.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// use ansi_escapers::Color;
+    /// let result = parse_ansi_annotated("\x1B[31;7mx");
+    /// assert_eq!(result.spans[0].effective_colors(), (None, Some(Color::Red)));
+    /// ```
+    pub fn effective_colors(&self) -> (Option<Color>, Option<Color>) {
+        let (mut fg, mut bg) = (None, None);
+        let (mut reverse, mut conceal) = (false, false);
+        for code in &self.codes {
+            match *code {
+                SgrAttribute::Reset => {
+                    (fg, bg) = (None, None);
+                    (reverse, conceal) = (false, false);
+                }
+                SgrAttribute::Foreground(color) => fg = Some(color),
+                SgrAttribute::Background(color) => bg = Some(color),
+                SgrAttribute::DefaultForeground => fg = None,
+                SgrAttribute::DefaultBackground => bg = None,
+                SgrAttribute::Reverse => reverse = true,
+                SgrAttribute::Conceal => conceal = true,
+                _ => {}
+            }
+        }
+        if reverse {
+            (fg, bg) = (bg, fg);
+        }
+        if conceal {
+            fg = bg;
+        }
+        (fg, bg)
+    }
+}
+
 /// Represents a point event (e.g., cursor move) at a position in the text.
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// Represents a point event (e.g., cursor move) at a position in the text.
 pub struct AnsiPoint {
-    /// Byte offset in the cleaned text where the event occurs.
+    /// Offset in the cleaned text where the event occurs (bytes, or chars in char-offset mode).
     pub pos: usize,
     /// The ANSI escape code at this position.
     pub code: AnsiEscape,
@@ -42,6 +97,545 @@ pub struct AnsiParseResult {
     pub spans: Vec<AnsiSpan>,
     /// Codes at specific positions in the text.
     pub points: Vec<AnsiPoint>,
+    /// Offsets of each `\n` in the text, in the configured unit.
+    ///
+    /// Only filled in when [`ParseOptions::line_breaks`] is enabled; empty otherwise.
+    pub line_breaks: Vec<usize>,
+}
+
+/// The kind of problem found by [`AnsiParser::parse_annotated_strict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiParseErrorKind {
+    /// The input ends before the sequence's final byte.
+    Truncated,
+    /// The sequence's final byte is not recognized (or not with these parameters).
+    UnknownFinalByte(u8),
+    /// An SGR color (`38`, `48` or `58`) has a missing or out-of-range parameter.
+    BadColorParam,
+}
+
+/// A malformed or unknown escape sequence found in strict parsing mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnsiParseError {
+    /// Byte offset in the input where the offending sequence starts.
+    pub offset: usize,
+    /// What is wrong with the sequence.
+    pub kind: AnsiParseErrorKind,
+}
+
+impl core::fmt::Display for AnsiParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            AnsiParseErrorKind::Truncated => {
+                write!(f, "truncated escape sequence at byte {}", self.offset)
+            }
+            AnsiParseErrorKind::UnknownFinalByte(b) => write!(
+                f,
+                "unknown escape sequence with final byte {:?} at byte {}",
+                b as char, self.offset
+            ),
+            AnsiParseErrorKind::BadColorParam => {
+                write!(f, "bad SGR color parameter at byte {}", self.offset)
+            }
+        }
+    }
+}
+
+impl core::error::Error for AnsiParseError {}
+
+impl AnsiParseResult {
+    /// Re-render the result as a string with escape codes.
+    ///
+    /// Each span's codes are emitted at its start and a reset at its end, and point codes
+    /// are emitted at their positions. Offsets are interpreted as byte offsets. Codes are
+    /// produced by `creator`, so colors are subject to its environment's capabilities.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, parse_ansi_annotated};
+    /// let result = parse_ansi_annotated("\x1B[1mbold\x1B[0m plain");
+    /// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+    ///     supports_ansi: true,
+    ///     ..AnsiEnvironment::default()
+    /// });
+    /// let rendered = result.render(&creator);
+    /// assert_eq!(parse_ansi_annotated(&rendered), result);
+    /// ```
+    pub fn render(&self, creator: &AnsiCreator) -> String {
+        // (position, order at that position, code): resets, then points, then span openings
+        let mut events: Vec<(usize, u8, String)> = Vec::new();
+        for span in &self.spans {
+            events.push((
+                span.end,
+                0,
+                creator.sgr_code(SgrAttribute::Reset).into_owned(),
+            ));
+            let open: String = span.codes.iter().map(|c| creator.sgr_code(*c)).collect();
+            events.push((span.start, 2, open));
+        }
+        for point in &self.points {
+            events.push((point.pos, 1, creator.escape_code(point.code.clone())));
+        }
+        events.sort_by_key(|(pos, order, _)| (*pos, *order));
+        // Adjacent spans only need a single reset between them
+        events.dedup_by(|b, a| a.0 == b.0 && a.1 == 0 && b.1 == 0);
+
+        let mut out = String::with_capacity(self.text.len());
+        let mut last = 0;
+        for (pos, _, code) in events {
+            let pos = pos.clamp(last, self.text.len());
+            if let Some(text) = self.text.get(last..pos) {
+                out.push_str(text);
+                last = pos;
+            }
+            out.push_str(&code);
+        }
+        out.push_str(&self.text[last..]);
+        out
+    }
+
+    /// Convert the result to IRC formatting codes.
+    ///
+    /// Colors become `\x03fg,bg` (8-bit and 24-bit colors are first mapped to the nearest
+    /// of the 16 named colors), and bold, italic, underline, reverse and crossed out
+    /// become `\x02`, `\x1D`, `\x1F`, `\x16` and `\x1E`. Each span ends with `\x0F`.
+    /// Points (cursor movement, erase, ...) are dropped. Offsets are interpreted as byte
+    /// offsets.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// let result = parse_ansi_annotated("\x1B[1;31mhi\x1B[0m!");
+    /// assert_eq!(result.to_irc(), "\x02\x0305hi\x0F!");
+    /// ```
+    pub fn to_irc(&self) -> String {
+        let mut out = String::with_capacity(self.text.len());
+        let mut last = 0;
+        for span in &self.spans {
+            let (start, end) = (span.start.max(last), span.end.min(self.text.len()));
+            let (Some(before), Some(inner)) =
+                (self.text.get(last..start), self.text.get(start..end))
+            else {
+                continue;
+            };
+            out.push_str(before);
+            let (mut fg, mut bg) = (None, None);
+            for code in &span.codes {
+                match code {
+                    SgrAttribute::Bold => out.push('\x02'),
+                    SgrAttribute::Italic => out.push('\x1D'),
+                    SgrAttribute::Underline => out.push('\x1F'),
+                    SgrAttribute::UnderlineStyle(kind) if *kind != UnderlineKind::None => {
+                        out.push('\x1F')
+                    }
+                    SgrAttribute::Reverse => out.push('\x16'),
+                    SgrAttribute::CrossedOut => out.push('\x1E'),
+                    SgrAttribute::Foreground(color) => fg = Some(irc_color(*color)),
+                    SgrAttribute::Background(color) => bg = Some(irc_color(*color)),
+                    _ => {}
+                }
+            }
+            match (fg, bg) {
+                (Some(fg), Some(bg)) => out.push_str(&format!("\x03{:02},{:02}", fg, bg)),
+                (Some(fg), None) => out.push_str(&format!("\x03{:02}", fg)),
+                // 99 is the "default color" in IRC
+                (None, Some(bg)) => out.push_str(&format!("\x0399,{:02}", bg)),
+                (None, None) => {}
+            }
+            out.push_str(inner);
+            out.push('\x0F');
+            last = end;
+        }
+        out.push_str(self.text.get(last..).unwrap_or_default());
+        out
+    }
+
+    /// Map a byte offset in `text` to the display column on its line.
+    ///
+    /// Wide characters count as two columns, tabs advance to the next multiple of
+    /// `tab_width`, and a newline resets the column to zero.
+    ///
+    /// # Arguments
+    /// * `byte_offset` - Byte offset into `text` (clamped to its length).
+    /// * `tab_width` - Width of a tab stop in columns.
+    pub fn byte_to_display_column(&self, byte_offset: usize, tab_width: usize) -> usize {
+        let mut col = 0;
+        for (idx, ch) in self.text.char_indices() {
+            if idx >= byte_offset {
+                break;
+            }
+            match ch {
+                '\n' => col = 0,
+                '\t' if tab_width > 0 => col += tab_width - col % tab_width,
+                _ => col += ch.width().unwrap_or(0),
+            }
+        }
+        col
+    }
+
+    /// Terminal cell width of the cleaned text.
+    ///
+    /// Wide characters (e.g. CJK) count as two columns and combining marks as zero. If the
+    /// text spans several lines, the width of the widest line is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// assert_eq!(parse_ansi_annotated("\x1B[1mab\x1B[0m\ncde").display_width(), 3);
+    /// ```
+    pub fn display_width(&self) -> usize {
+        self.text
+            .lines()
+            .map(|line| line.width())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Focus events in order of appearance: `true` for focus gained, `false` for focus lost.
+    ///
+    /// Terminals only send these after focus reporting is enabled with
+    /// [`AnsiCreator::focus_reporting`].
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// assert_eq!(parse_ansi_annotated("\x1B[I\x1B[O").focus_events(), vec![true, false]);
+    /// ```
+    pub fn focus_events(&self) -> Vec<bool> {
+        self.points
+            .iter()
+            .filter_map(|p| match p.code {
+                AnsiEscape::Focus { gained } => Some(gained),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Find the first text area size report (`ESC [ 8 ; rows ; cols t`) and return
+    /// `(rows, cols)`.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// assert_eq!(parse_ansi_annotated("\x1B[8;24;80t").window_size(), Some((24, 80)));
+    /// ```
+    pub fn window_size(&self) -> Option<(u16, u16)> {
+        self.points.iter().find_map(|p| match &p.code {
+            AnsiEscape::WindowReport { kind: 8, values } => match values[..] {
+                [rows, cols] => Some((rows, cols)),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Convert the result into ratatui [`Text`](ratatui::text::Text), with one `Line` per
+    /// line of text and one `Span` per run of a single style.
+    ///
+    /// Colors and the attributes ratatui has modifiers for (bold, faint, italic,
+    /// underline, blink, reverse, conceal, crossed out) are carried over; others (overline,
+    /// underline color) are dropped. A `\r` before each `\n` is dropped with it. Offsets
+    /// are expected in bytes (the default), not chars.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// let text = parse_ansi_annotated("\x1B[1mtitle\x1B[0m\nbody").into_ratatui_text();
+    /// assert_eq!(text.lines.len(), 2);
+    /// ```
+    #[cfg(feature = "ratatui")]
+    pub fn into_ratatui_text(&self) -> ratatui::text::Text<'static> {
+        use ratatui::text::{Line, Span, Text};
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for line in self.text.split('\n') {
+            let end = start + line.strip_suffix('\r').unwrap_or(line).len();
+            let mut segments = Vec::new();
+            if end > start {
+                self.push_styled_segments(start, end, &mut segments);
+            }
+            let spans: Vec<Span<'static>> = segments
+                .into_iter()
+                .map(|(text, codes)| Span::styled(text, ratatui_style(&codes)))
+                .collect();
+            lines.push(Line::from(spans));
+            start += line.len() + 1;
+        }
+        Text::from(lines)
+    }
+
+    /// Split the text on whitespace into words, each with the SGR attributes active on it.
+    ///
+    /// A word that crosses a style change is split at the change, giving one segment per
+    /// style, so every segment has a single style. Whitespace is dropped. Offsets are
+    /// expected in bytes (the default), not chars.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// use ansi_escapers::{Color, SgrAttribute};
+    /// let words = parse_ansi_annotated("plain \x1B[31mred\x1B[0m").styled_words();
+    /// assert_eq!(words[0], ("plain".to_string(), vec![]));
+    /// assert_eq!(words[1], ("red".to_string(), vec![SgrAttribute::Foreground(Color::Red)]));
+    /// ```
+    pub fn styled_words(&self) -> Vec<(String, Vec<SgrAttribute>)> {
+        let mut words = Vec::new();
+        let mut start = None;
+        for (idx, ch) in self.text.char_indices().chain([(self.text.len(), ' ')]) {
+            match (start, ch.is_whitespace()) {
+                (None, false) => start = Some(idx),
+                (Some(word_start), true) => {
+                    self.push_styled_segments(word_start, idx, &mut words);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        words
+    }
+
+    /// Push the segments of `text[start..end]`, split wherever a span starts or ends.
+    fn push_styled_segments(
+        &self,
+        start: usize,
+        end: usize,
+        out: &mut Vec<(String, Vec<SgrAttribute>)>,
+    ) {
+        let mut cuts: Vec<usize> = self
+            .spans
+            .iter()
+            .flat_map(|span| [span.start, span.end])
+            .filter(|&pos| pos > start && pos < end && self.text.is_char_boundary(pos))
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mut from = start;
+        for to in cuts.into_iter().chain([end]) {
+            let codes = self
+                .spans
+                .iter()
+                .find(|span| span.start <= from && from < span.end)
+                .map(|span| span.codes.clone())
+                .unwrap_or_default();
+            out.push((self.text[from..to].to_string(), codes));
+            from = to;
+        }
+    }
+
+    /// Ranges of the text that were pasted while bracketed paste mode (private mode 2004)
+    /// was on, i.e. that fall between an [`AnsiEscape::PasteStart`] and the following
+    /// [`AnsiEscape::PasteEnd`].
+    ///
+    /// Ranges are in the configured offset unit (bytes by default). A paste with no end
+    /// marker is not reported.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// let result = parse_ansi_annotated("> \x1B[200~ls -l\x1B[201~");
+    /// assert_eq!(result.paste_regions(), vec![2..7]);
+    /// ```
+    pub fn paste_regions(&self) -> Vec<Range<usize>> {
+        let mut regions = Vec::new();
+        let mut start = None;
+        for point in &self.points {
+            match point.code {
+                AnsiEscape::PasteStart => start = start.or(Some(point.pos)),
+                AnsiEscape::PasteEnd => {
+                    if let Some(start) = start.take() {
+                        regions.push(start..point.pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+        regions
+    }
+
+    /// Whether the cursor is visible at the end of the stream, according to the last
+    /// [`DeviceControl::HideCursor`] or [`DeviceControl::ShowCursor`].
+    ///
+    /// Returns `None` if the visibility was never changed.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// let result = parse_ansi_annotated("\x1B[?25lworking...\x1B[?25h");
+    /// assert_eq!(result.final_cursor_visible(), Some(true));
+    /// ```
+    pub fn final_cursor_visible(&self) -> Option<bool> {
+        self.points.iter().rev().find_map(|point| match point.code {
+            AnsiEscape::Device(DeviceControl::HideCursor) => Some(false),
+            AnsiEscape::Device(DeviceControl::ShowCursor) => Some(true),
+            _ => None,
+        })
+    }
+
+    /// The cursor shape at the end of the stream, according to the last
+    /// [`DeviceControl::SetCursorStyle`].
+    ///
+    /// Returns `None` if the shape was never changed.
+    pub fn final_cursor_style(&self) -> Option<CursorStyle> {
+        self.points.iter().rev().find_map(|point| match point.code {
+            AnsiEscape::Device(DeviceControl::SetCursorStyle(style)) => Some(style),
+            _ => None,
+        })
+    }
+
+    /// Merge SGR spans that follow each other with no text in between.
+    ///
+    /// A zero-length span (a style change immediately followed by another) is folded into
+    /// the span that starts where it ends, taking the union of their attributes; later
+    /// attributes replace earlier ones of the same kind. Adjacent spans with identical
+    /// attributes are joined into one. Zero-length spans with nothing to merge into are
+    /// dropped, as they affect no text. Spans are expected in order of position.
+    ///
+    /// The parser already folds consecutive style changes into the span they apply to, so
+    /// on its output this does the same as [`coalesce`](Self::coalesce). The zero-length
+    /// handling is for results built or edited by hand.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::{AnsiParseResult, AnsiSpan};
+    /// use ansi_escapers::SgrAttribute;
+    /// let mut result = AnsiParseResult {
+    ///     text: "ab".to_string(),
+    ///     spans: vec![
+    ///         AnsiSpan { start: 0, end: 1, codes: vec![SgrAttribute::Bold] },
+    ///         AnsiSpan { start: 1, end: 2, codes: vec![SgrAttribute::Bold] },
+    ///     ],
+    ///     points: vec![],
+    ///     line_breaks: vec![],
+    /// };
+    /// result.collapse_adjacent_sgr();
+    /// assert_eq!(result.spans, vec![AnsiSpan { start: 0, end: 2, codes: vec![SgrAttribute::Bold] }]);
+    /// ```
+    pub fn collapse_adjacent_sgr(&mut self) {
+        let mut collapsed: Vec<AnsiSpan> = Vec::with_capacity(self.spans.len());
+        for span in core::mem::take(&mut self.spans) {
+            if let Some(prev) = collapsed.last_mut()
+                && prev.end == span.start
+                && prev.start == prev.end
+            {
+                let mut active = BTreeSet::new();
+                for sgr in prev.codes.iter().chain(&span.codes) {
+                    apply_sgr(&mut active, *sgr);
+                }
+                *prev = AnsiSpan {
+                    start: span.start,
+                    end: span.end,
+                    codes: active.into_iter().collect(),
+                };
+                continue;
+            }
+            collapsed.push(span);
+        }
+        collapsed.retain(|span| span.start != span.end);
+        self.spans = collapsed;
+        self.coalesce();
+    }
+
+    /// Merge consecutive spans that have equal codes and touching ranges.
+    ///
+    /// This happens when a style is reset and then applied again with no text in between
+    /// (e.g. `ESC[31ma ESC[0m ESC[31mb`).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// let mut result = parse_ansi_annotated("\x1B[31ma\x1B[0m\x1B[31mb");
+    /// assert_eq!(result.spans.len(), 2);
+    /// result.coalesce();
+    /// assert_eq!(result.spans.len(), 1);
+    /// ```
+    pub fn coalesce(&mut self) {
+        let mut merged: Vec<AnsiSpan> = Vec::with_capacity(self.spans.len());
+        for span in core::mem::take(&mut self.spans) {
+            if let Some(prev) = merged.last_mut()
+                && prev.end == span.start
+                && prev.codes == span.codes
+            {
+                prev.end = span.end;
+                continue;
+            }
+            merged.push(span);
+        }
+        self.spans = merged;
+    }
+
+    /// Keep only the points for which `f` returns `true`. Text and spans are untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::parse_ansi_annotated;
+    /// use ansi_escapers::AnsiEscape;
+    /// let mut result = parse_ansi_annotated("a\x1B[2Ab\x1B[2J");
+    /// result.filter_points(|p| !matches!(p.code, AnsiEscape::Cursor(_)));
+    /// assert_eq!(result.points.len(), 1);
+    /// ```
+    pub fn filter_points<F: FnMut(&AnsiPoint) -> bool>(&mut self, f: F) {
+        self.points.retain(f);
+    }
+
+    /// Replace every point with the result of `f`, e.g. to shift positions.
+    /// Text and spans are untouched.
+    pub fn map_points<F: FnMut(AnsiPoint) -> AnsiPoint>(&mut self, f: F) {
+        self.points = core::mem::take(&mut self.points)
+            .into_iter()
+            .map(f)
+            .collect();
+    }
+}
+
+/// Options controlling how [`AnsiParser`] interprets its input.
+///
+/// Each option can also be set with the matching `AnsiParser::with_*` builder method.
+/// New options may be added, so build a value from [`ParseOptions::default`] and set the
+/// fields you need rather than with a struct literal.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::{AnsiParser, ParseOptions};
+/// let mut options = ParseOptions::default();
+/// options.strip_lone_esc = true;
+/// let result = AnsiParser::new("\x1Bx").with_options(options).parse_annotated();
+/// assert_eq!(result.text, "x");
+/// // The same through the builder
+/// assert_eq!(AnsiParser::new("\x1Bx").with_strip_lone_esc(true).parse_annotated().text, "x");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Report offsets in chars instead of bytes (default `false`).
+    pub char_offsets: bool,
+    /// Drop ESC bytes that do not start a sequence (default `false`).
+    pub strip_lone_esc: bool,
+    /// Separator between SGR and cursor parameters (default `;`).
+    pub param_separator: char,
+    /// Report unrecognized CSI sequences as [`AnsiEscape::Unknown`] (default `false`).
+    pub preserve_unknown: bool,
+    /// Record the offset of each `\n` in [`AnsiParseResult::line_breaks`] (default `false`).
+    pub line_breaks: bool,
+    /// Let erase codes end or truncate the open span (default `false`).
+    pub erase_closes_spans: bool,
+    /// Treat `:` in SGR parameters as a separator between attributes, like `;`, except
+    /// within colors and underline styles (default `false`).
+    pub sgr_all_colon: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            char_offsets: false,
+            strip_lone_esc: false,
+            param_separator: ';',
+            preserve_unknown: false,
+            line_breaks: false,
+            erase_closes_spans: false,
+            sgr_all_colon: false,
+        }
+    }
 }
 
 /// Skeleton for the ANSI escape code parser.
@@ -50,8 +644,9 @@ pub struct AnsiParseResult {
 pub struct AnsiParser<'a> {
     input: &'a str,
     pos: usize,
-    output_pos: usize, // Position in the cleaned text
-                       // Additional state fields as needed
+    output_pos: usize,   // Position in the cleaned text
+    output_chars: usize, // Position in the cleaned text, in chars
+    options: ParseOptions,
 }
 
 impl<'a> AnsiParser<'a> {
@@ -64,7 +659,198 @@ impl<'a> AnsiParser<'a> {
             input,
             pos: 0,
             output_pos: 0,
+            output_chars: 0,
+            options: ParseOptions::default(),
+        }
+    }
+
+    /// Replace all parser options at once.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Report span and point offsets as character (Unicode scalar value) counts
+    /// into the cleaned text instead of byte offsets.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// let result = AnsiParser::new("é\x1B[1mx").with_char_offsets(true).parse_annotated();
+    /// assert_eq!(result.spans[0].start, 1);
+    /// ```
+    pub fn with_char_offsets(mut self, enabled: bool) -> Self {
+        self.options.char_offsets = enabled;
+        self
+    }
+
+    /// Drop orphan ESC bytes instead of copying them into the cleaned text.
+    ///
+    /// An orphan ESC is one that is not followed by a sequence introducer (`[`, `]`, `P`,
+    /// `_`, `^` or `X`), including an ESC at the very end of the input. By default such
+    /// bytes are kept as text.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// let result = AnsiParser::new("\x1Bx").with_strip_lone_esc(true).parse_annotated();
+    /// assert_eq!(result.text, "x");
+    /// ```
+    pub fn with_strip_lone_esc(mut self, enabled: bool) -> Self {
+        self.options.strip_lone_esc = enabled;
+        self
+    }
+
+    /// Use `separator` instead of `;` between SGR and cursor movement parameters.
+    ///
+    /// Some legacy terminals separate parameters with a comma (e.g. `ESC [ 1 , 31 m`).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// use ansi_escapers::{Color, SgrAttribute};
+    /// let result = AnsiParser::new("\x1B[1,31mx").with_param_separator(',').parse_annotated();
+    /// assert_eq!(
+    ///     result.spans[0].codes,
+    ///     vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)]
+    /// );
+    /// ```
+    pub fn with_param_separator(mut self, separator: char) -> Self {
+        self.options.param_separator = separator;
+        self
+    }
+
+    /// Report CSI sequences that match no handler as [`AnsiEscape::Unknown`] points
+    /// instead of dropping them, so they can be re-emitted when rendering.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// use ansi_escapers::AnsiEscape;
+    /// let result = AnsiParser::new("\x1B[999y").with_preserve_unknown(true).parse_annotated();
+    /// assert!(matches!(result.points[0].code, AnsiEscape::Unknown { .. }));
+    /// ```
+    pub fn with_preserve_unknown(mut self, enabled: bool) -> Self {
+        self.options.preserve_unknown = enabled;
+        self
+    }
+
+    /// Record where line breaks fall in the cleaned text (default `false`).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// let result = AnsiParser::new("a\x1B[1mb\ncd\n").with_line_breaks(true).parse_annotated();
+    /// assert_eq!(result.line_breaks, vec![2, 5]);
+    /// ```
+    pub fn with_line_breaks(mut self, enabled: bool) -> Self {
+        self.options.line_breaks = enabled;
+        self
+    }
+
+    /// Let erase codes affect the span that is open when they occur (default `false`).
+    ///
+    /// Erasing to the end of the line or display (`ESC [ K`, `ESC [ J`) closes the open
+    /// span at the erase and starts a new one with the same codes. Erasing to the start
+    /// (`ESC [ 1 K`) or everything (`ESC [ 2 K`) moves the open span's start to the erase,
+    /// since the text styled before it has been erased.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// let result = AnsiParser::new("\x1B[31mABC\x1B[1KDEF")
+    ///     .with_erase_closes_spans(true)
+    ///     .parse_annotated();
+    /// assert_eq!((result.spans[0].start, result.spans[0].end), (3, 6));
+    /// ```
+    pub fn with_erase_closes_spans(mut self, enabled: bool) -> Self {
+        self.options.erase_closes_spans = enabled;
+        self
+    }
+
+    /// Read SGR parameters that use `:` as their only separator (e.g. `ESC [ 1 : 31 : 4 m`).
+    ///
+    /// Colons then separate attributes instead of sub-parameters, except in a parameter
+    /// that starts with a color (`38`, `48`, `58`) or underline style (`4`). Those keep
+    /// their sub-parameters, so `38:2:<id>:r:g:b` and `4:3` are read as usual.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::AnsiParser;
+    /// use ansi_escapers::SgrAttribute;
+    /// let result = AnsiParser::new("\x1B[1:4mx").with_sgr_all_colon(true).parse_annotated();
+    /// assert_eq!(result.spans[0].codes, vec![SgrAttribute::Bold, SgrAttribute::Underline]);
+    /// ```
+    pub fn with_sgr_all_colon(mut self, enabled: bool) -> Self {
+        self.options.sgr_all_colon = enabled;
+        self
+    }
+
+    /// SGR parameters with colons turned into separators if `sgr_all_colon` is set.
+    ///
+    /// Tokens for colors (`38`, `48`, `58`) and underline styles (`4`) keep their colons,
+    /// as sub-parameters for [`parse_sgr_subparams`].
+    fn sgr_params<'p>(&self, params: &'p str) -> Cow<'p, str> {
+        if !self.options.sgr_all_colon || !params.contains(':') {
+            return Cow::Borrowed(params);
+        }
+        let separator = self.options.param_separator;
+        let mut out = String::with_capacity(params.len());
+        for (i, token) in params.split(separator).enumerate() {
+            if i > 0 {
+                out.push(separator);
+            }
+            if matches!(token.split(':').next(), Some("38" | "48" | "58" | "4")) {
+                out.push_str(token);
+            } else {
+                out.extend(token.chars().map(|c| if c == ':' { separator } else { c }));
+            }
+        }
+        Cow::Owned(out)
+    }
+
+    /// Current offset in the cleaned text, in the configured unit.
+    fn offset(&self) -> usize {
+        if self.options.char_offsets {
+            self.output_chars
+        } else {
+            self.output_pos
+        }
+    }
+
+    /// Append a run of plain text to the cleaned text.
+    fn push_text(&mut self, cleaned: &mut String, line_breaks: &mut Vec<usize>, text: &str) {
+        if self.options.line_breaks {
+            text.chars()
+                .for_each(|ch| self.push_char(cleaned, line_breaks, ch));
+            return;
+        }
+        cleaned.push_str(text);
+        self.output_pos += text.len();
+        // Counting chars is only worth it when offsets are reported in chars
+        if self.options.char_offsets {
+            self.output_chars += text.chars().count();
+        }
+    }
+
+    /// Byte position of the next byte after the current one that could start an escape
+    /// sequence (ESC, or the lead byte of C1 CSI), or the end of the input.
+    ///
+    /// Neither byte can appear inside a multi-byte character, so the result is always on
+    /// a char boundary.
+    fn next_escape_candidate(&self) -> usize {
+        let bytes = self.input.as_bytes();
+        memchr2(0x1B, 0xC2, &bytes[self.pos + 1..]).map_or(bytes.len(), |i| self.pos + 1 + i)
+    }
+
+    /// Append a character to the cleaned text, recording it if it is a line break.
+    fn push_char(&mut self, cleaned: &mut String, line_breaks: &mut Vec<usize>, ch: char) {
+        if ch == '\n' && self.options.line_breaks {
+            line_breaks.push(self.offset());
         }
+        cleaned.push(ch);
+        self.output_pos += ch.len_utf8();
+        self.output_chars += 1;
     }
 
     /// Main entry point: parses the input and returns an annotated parse result.
@@ -74,7 +860,7 @@ impl<'a> AnsiParser<'a> {
         let mut cleaned = String::with_capacity(self.input.len());
         let mut spans = Vec::new();
         let mut points = Vec::new();
-        use std::collections::BTreeSet;
+        let mut line_breaks = Vec::new();
         let mut active_sgrs = BTreeSet::new(); // BTreeSet for deterministic order
         let mut current_span_start: Option<usize> = None;
         let mut last_emitted_sgrs = BTreeSet::new();
@@ -82,69 +868,68 @@ impl<'a> AnsiParser<'a> {
         while self.pos < self.input.len() {
             if let Some((escapes, consumed)) = self.parse_next_escapes() {
                 for escape in escapes {
+                    // REP expands into the cleaned text, inside whatever span is open
+                    if let AnsiEscape::Repeat(count) = escape {
+                        if let Some(ch) = cleaned.chars().next_back() {
+                            for _ in 0..count {
+                                self.push_char(&mut cleaned, &mut line_breaks, ch);
+                            }
+                        }
+                        continue;
+                    }
                     // Only add non-SGR codes to points
                     if !matches!(escape, AnsiEscape::Sgr(_)) {
                         points.push(AnsiPoint {
-                            pos: self.output_pos,
+                            pos: self.offset(),
                             code: escape.clone(),
                         });
                     }
 
+                    if let AnsiEscape::Erase(erase) = escape
+                        && self.options.erase_closes_spans
+                        && let Some(start) = current_span_start
+                    {
+                        let (Erase::Display(mode) | Erase::Line(mode)) = erase;
+                        if mode == EraseMode::ToEnd {
+                            spans.push(AnsiSpan {
+                                start,
+                                end: self.offset(),
+                                codes: last_emitted_sgrs.iter().cloned().collect(),
+                            });
+                        }
+                        current_span_start = Some(self.offset());
+                    }
+
                     if let AnsiEscape::Sgr(sgr) = &escape {
                         match sgr {
                             SgrAttribute::Reset => {
                                 // If there was an active span, close it
-                                if let Some(start) = current_span_start.take() {
-                                    if !last_emitted_sgrs.is_empty() {
-                                        spans.push(AnsiSpan {
-                                            start,
-                                            end: self.output_pos,
-                                            codes: last_emitted_sgrs.iter().cloned().collect(),
-                                        });
-                                    }
+                                if let Some(start) = current_span_start.take()
+                                    && !last_emitted_sgrs.is_empty()
+                                {
+                                    spans.push(AnsiSpan {
+                                        start,
+                                        end: self.offset(),
+                                        codes: last_emitted_sgrs.iter().cloned().collect(),
+                                    });
                                 }
                                 active_sgrs.clear();
                             }
-                            _ => {
-                                // If this SGR is already active, replace it (remove old, insert new)
-                                // Remove any previous instance of the same SGR "type"
-                                // For Foreground/Background/UnderlineColor, remove any previous of that type
-                                match sgr {
-                                    SgrAttribute::Foreground(_) => {
-                                        active_sgrs
-                                            .retain(|a| !matches!(a, SgrAttribute::Foreground(_)));
-                                    }
-                                    SgrAttribute::Background(_) => {
-                                        active_sgrs
-                                            .retain(|a| !matches!(a, SgrAttribute::Background(_)));
-                                    }
-                                    SgrAttribute::UnderlineColor(_) => {
-                                        active_sgrs.retain(|a| {
-                                            !matches!(a, SgrAttribute::UnderlineColor(_))
-                                        });
-                                    }
-                                    _ => {
-                                        active_sgrs.retain(|a| {
-                                            std::mem::discriminant(a) != std::mem::discriminant(sgr)
-                                        });
-                                    }
-                                }
-                                active_sgrs.insert(sgr.clone());
-                            }
+                            _ => apply_sgr(&mut active_sgrs, *sgr),
                         }
                         // If the set of active SGRs changed, close the previous span and start a new one
                         if active_sgrs != last_emitted_sgrs {
-                            if let Some(start) = current_span_start.take() {
-                                if !last_emitted_sgrs.is_empty() {
-                                    spans.push(AnsiSpan {
-                                        start,
-                                        end: self.output_pos,
-                                        codes: last_emitted_sgrs.iter().cloned().collect(),
-                                    });
-                                }
+                            if let Some(start) = current_span_start.take()
+                                && !last_emitted_sgrs.is_empty()
+                            {
+                                spans.push(AnsiSpan {
+                                    start,
+                                    end: self.offset(),
+                                    codes: last_emitted_sgrs.iter().cloned().collect(),
+                                });
                             }
                             if !active_sgrs.is_empty() {
-                                current_span_start = Some(self.output_pos);
+                                current_span_start = Some(self.offset());
                             }
                             last_emitted_sgrs = active_sgrs.clone();
                         }
@@ -152,26 +937,23 @@ impl<'a> AnsiParser<'a> {
                 }
                 self.pos += consumed;
             } else {
-                // Copy non-escape character to cleaned text
-                if let Some(ch) = self.input[self.pos..].chars().next() {
-                    cleaned.push(ch);
-                    self.pos += ch.len_utf8();
-                    self.output_pos += ch.len_utf8();
-                } else {
-                    // Should not happen, but break to avoid infinite loop
-                    break;
-                }
+                // Copy text up to the next possible escape in one go, so input without
+                // escapes is copied in a single step
+                let end = self.next_escape_candidate();
+                let text = &self.input[self.pos..end];
+                self.push_text(&mut cleaned, &mut line_breaks, text);
+                self.pos = end;
             }
         }
         // If a span is still open at the end, close it
-        if let Some(start) = current_span_start.take() {
-            if !last_emitted_sgrs.is_empty() {
-                spans.push(AnsiSpan {
-                    start,
-                    end: self.output_pos,
-                    codes: last_emitted_sgrs.iter().cloned().collect(),
-                });
-            }
+        if let Some(start) = current_span_start.take()
+            && !last_emitted_sgrs.is_empty()
+        {
+            spans.push(AnsiSpan {
+                start,
+                end: self.offset(),
+                codes: last_emitted_sgrs.iter().cloned().collect(),
+            });
         }
         // Filter out spans with matching start and end positions
         let spans = spans
@@ -183,6 +965,86 @@ impl<'a> AnsiParser<'a> {
             text: cleaned,
             spans,
             points,
+            line_breaks,
+        }
+    }
+
+    /// Like [`AnsiParser::parse_annotated`], but fail on the first malformed or unknown
+    /// escape sequence instead of silently skipping it.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::{AnsiParseErrorKind, AnsiParser};
+    /// let err = AnsiParser::new("ok\x1B[").parse_annotated_strict().unwrap_err();
+    /// assert_eq!(err.offset, 2);
+    /// assert_eq!(err.kind, AnsiParseErrorKind::Truncated);
+    /// ```
+    pub fn parse_annotated_strict(&mut self) -> Result<AnsiParseResult, AnsiParseError> {
+        let start = self.pos;
+        while self.pos < self.input.len() {
+            if let Some(kind) = self.sequence_error() {
+                let offset = self.pos;
+                self.pos = start;
+                return Err(AnsiParseError { offset, kind });
+            }
+            match self.parse_next_escapes() {
+                Some((_, consumed)) => self.pos += consumed,
+                None => {
+                    self.pos += self.input[self.pos..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8)
+                }
+            }
+        }
+        self.pos = start;
+        Ok(self.parse_annotated())
+    }
+
+    /// Check the CSI sequence at the current position, if any, for problems.
+    fn sequence_error(&self) -> Option<AnsiParseErrorKind> {
+        let bytes = &self.input.as_bytes()[self.pos..];
+        if !(bytes.starts_with(b"\x1B[") || bytes.starts_with(&[0xC2, 0x9B])) {
+            return None;
+        }
+        let Some(end) = bytes[2..]
+            .iter()
+            .position(|b| (0x40..=0x7E).contains(b) || matches!(b, 0x18 | 0x1A | 0x1B))
+            .filter(|&end| (0x40..=0x7E).contains(&bytes[2 + end]))
+        else {
+            return Some(AnsiParseErrorKind::Truncated);
+        };
+        let final_byte = bytes[2 + end];
+        let params = &self.input[self.pos + 2..self.pos + 2 + end];
+        if final_byte == b'm' && !params.starts_with('<') {
+            return (!sgr_colors_valid(&self.sgr_params(params), self.options.param_separator))
+                .then_some(AnsiParseErrorKind::BadColorParam);
+        }
+        match self.parse_next_escapes() {
+            Some((escapes, _)) if matches!(escapes[..], [] | [AnsiEscape::Unknown { .. }]) => {
+                Some(AnsiParseErrorKind::UnknownFinalByte(final_byte))
+            }
+            _ => None,
+        }
+    }
+
+    /// Consume the parser and return a lazy iterator over text runs and escape codes.
+    ///
+    /// Unlike [`AnsiParser::parse_annotated`], no spans or points are built, so input can be
+    /// processed in constant memory. A sequence containing several SGR attributes yields
+    /// one [`Event::Escape`] per attribute. Text is borrowed from the input, so REP is
+    /// reported as an [`AnsiEscape::Repeat`] escape rather than expanded.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::interpreter::{AnsiParser, Event};
+    /// let events: Vec<Event> = AnsiParser::new("a\x1B[1mb").events().collect();
+    /// assert_eq!(events.len(), 3);
+    /// ```
+    pub fn events(self) -> AnsiEvents<'a> {
+        AnsiEvents {
+            parser: self,
+            pending: Vec::new().into_iter(),
         }
     }
 
@@ -191,10 +1053,55 @@ impl<'a> AnsiParser<'a> {
     fn parse_next_escapes(&self) -> Option<(Vec<AnsiEscape>, usize)> {
         let bytes = self.input.as_bytes();
         if self.pos + 2 > bytes.len() {
-            return None;
+            return self.lone_esc();
         }
-        // Check for ESC [
-        if bytes[self.pos] == 0x1B && bytes[self.pos + 1] == b'[' {
+        // Two-byte escapes (ESC <char>) rather than CSI sequences
+        if bytes[self.pos] == 0x1B {
+            let escape = match bytes[self.pos + 1] {
+                b'H' => Some(AnsiEscape::Tab(TabOp::Set)),
+                b'7' => Some(AnsiEscape::Device(DeviceControl::SaveCursor)),
+                b'8' => Some(AnsiEscape::Device(DeviceControl::RestoreCursor)),
+                b'c' => Some(AnsiEscape::Device(DeviceControl::FullReset)),
+                b'M' => Some(AnsiEscape::Device(DeviceControl::ReverseIndex)),
+                _ => None,
+            };
+            if let Some(escape) = escape {
+                return Some((vec![escape], 2));
+            }
+            // Keypad mode changes (DECKPAM `ESC =`, DECKPNM `ESC >`) are not modelled
+            if matches!(bytes[self.pos + 1], b'=' | b'>') {
+                return Some((vec![], 2));
+            }
+        }
+        // nF escapes such as charset designation (`ESC ( B`) are not modelled either
+        if let Some(consumed) = nf_escape_len(&bytes[self.pos..]) {
+            return Some((vec![], consumed));
+        }
+        // Linux console palette sequences have a fixed length and no OSC terminator
+        if let Some(device) = parse_linux_palette(&bytes[self.pos..]) {
+            let consumed = if device == DeviceControl::LinuxPaletteReset {
+                3
+            } else {
+                10
+            };
+            return Some((vec![AnsiEscape::Device(device)], consumed));
+        }
+        // OSC 133 shell integration markers; other OSC strings are dropped below
+        if let Some((body, consumed)) = parse_osc(&bytes[self.pos..])
+            && let Some(mark) = parse_prompt_mark(body)
+        {
+            return Some((vec![AnsiEscape::ShellIntegration(mark)], consumed));
+        }
+        // Other complete control strings (OSC, DCS, APC, PM, SOS) are dropped
+        if let Some(consumed) = control_string_len(&bytes[self.pos..]) {
+            return Some((vec![], consumed));
+        }
+        // Check for ESC [ or the C1 CSI character U+009B (encoded as 0xC2 0x9B). Both
+        // introducers are two bytes long, and since `pos` is always on a char boundary a
+        // 0x9B continuation byte inside another character is never mistaken for CSI.
+        if (bytes[self.pos] == 0x1B && bytes[self.pos + 1] == b'[')
+            || (bytes[self.pos] == 0xC2 && bytes[self.pos + 1] == 0x9B)
+        {
             // Find the end of the CSI sequence (final byte is 0x40-0x7E)
             let mut end = self.pos + 2;
             while end < bytes.len() {
@@ -202,6 +1109,10 @@ impl<'a> AnsiParser<'a> {
                 if (0x40..=0x7E).contains(&b) {
                     break;
                 }
+                // CAN and SUB cancel the sequence, and ESC starts a new one
+                if matches!(b, 0x18 | 0x1A | 0x1B) {
+                    return Some((vec![], end - self.pos));
+                }
                 end += 1;
             }
             if end >= bytes.len() {
@@ -214,31 +1125,90 @@ impl<'a> AnsiParser<'a> {
             let params = &self.input[self.pos + 2..end];
             let consumed = end + 1 - self.pos;
             let mut escapes = Vec::new();
-            // SGR (m)
-            if final_byte == b'm' {
-                let sgrs = parse_sgr(params);
+            // SGR mouse events share the 'm' final byte with SGR, so check them first
+            if let Some(mouse) = parse_mouse(params, final_byte) {
+                escapes.push(mouse);
+            } else if final_byte == b'm' {
+                // SGR (m)
+                let sgrs = parse_sgr(&self.sgr_params(params), self.options.param_separator);
                 for sgr in sgrs {
                     escapes.push(AnsiEscape::Sgr(sgr));
                 }
-            } else if let Some(cursor) = parse_cursor(params, final_byte) {
+            } else if let Some(cursor) =
+                parse_cursor(params, final_byte, self.options.param_separator)
+            {
                 escapes.push(AnsiEscape::Cursor(cursor));
+            } else if let Some(report) = parse_report(params, final_byte) {
+                // Reports come before tabs: a bare "ESC [ I" is a focus-in event, not CHT
+                escapes.push(report);
+            } else if let Some(tab) = parse_tab(params, final_byte) {
+                escapes.push(AnsiEscape::Tab(tab));
+            } else if let Some(scroll) = parse_scroll(params, final_byte) {
+                escapes.push(scroll);
+            } else if final_byte == b'b' {
+                // REP: repeat the preceding character; a count of 0 means 1 (ECMA-48)
+                escapes.push(AnsiEscape::Repeat(parse_count(params).unwrap_or(1).max(1)));
             } else if let Some(erase) = parse_erase(params, final_byte) {
                 escapes.push(AnsiEscape::Erase(erase));
+            } else if let Some(modes) = parse_private_modes(params, final_byte) {
+                escapes.extend(modes);
             } else if let Some(device) = parse_device(params, final_byte) {
                 escapes.push(AnsiEscape::Device(device));
             }
+            if escapes.is_empty() && self.options.preserve_unknown {
+                escapes.push(AnsiEscape::Unknown {
+                    params: params.to_string(),
+                    final_byte,
+                });
+            }
             // Always skip the escape sequence in the cleaned text, even if unknown
             return Some((escapes, consumed));
         }
-        None
+        if matches!(bytes[self.pos + 1], b'[' | b']' | b'P' | b'_' | b'^' | b'X') {
+            return None;
+        }
+        self.lone_esc()
+    }
+
+    /// Skip an ESC byte at the current position if orphan ESCs are being stripped.
+    fn lone_esc(&self) -> Option<(Vec<AnsiEscape>, usize)> {
+        if self.options.strip_lone_esc && self.input.as_bytes().get(self.pos) == Some(&0x1B) {
+            Some((vec![], 1))
+        } else {
+            None
+        }
+    }
+}
+
+/// Apply a (non-reset) SGR attribute to the set of active attributes.
+///
+/// An attribute replaces any active attribute of the same kind (e.g. a new foreground
+/// color replaces the previous one). "Off" codes such as `NotOverline` or
+/// `DefaultForeground` remove the attributes they cancel and are not added themselves.
+fn apply_sgr(active: &mut BTreeSet<SgrAttribute>, sgr: SgrAttribute) {
+    match sgr {
+        _ if sgr.is_off() => active.retain(|a| !sgr.turns_off(a)),
+        _ => {
+            active.retain(|a| !a.same_kind(&sgr));
+            active.insert(sgr);
+        }
     }
 }
 
 /// Parse SGR parameters (e.g., "1;31").
-fn parse_sgr(params: &str) -> Vec<SgrAttribute> {
+fn parse_sgr(params: &str, separator: char) -> Vec<SgrAttribute> {
+    // An empty parameter list (`ESC [ m`) is equivalent to `ESC [ 0 m`
+    if params.is_empty() {
+        return vec![SgrAttribute::Reset];
+    }
     let mut result = Vec::new();
-    let mut iter = params.split(';').filter(|s| !s.is_empty());
+    let mut iter = params.split(separator).filter(|s| !s.is_empty());
     while let Some(param) = iter.next() {
+        // A parameter with colon sub-parameters is self-contained (e.g. `4:3`, `38:2::r:g:b`)
+        if param.contains(':') && separator != ':' {
+            result.extend(parse_sgr_subparams(param));
+            continue;
+        }
         match param {
             "0" => result.push(SgrAttribute::Reset),
             "1" => result.push(SgrAttribute::Bold),
@@ -250,6 +1220,15 @@ fn parse_sgr(params: &str) -> Vec<SgrAttribute> {
             "7" => result.push(SgrAttribute::Reverse),
             "8" => result.push(SgrAttribute::Conceal),
             "9" => result.push(SgrAttribute::CrossedOut),
+            "53" => result.push(SgrAttribute::Overline),
+            "55" => result.push(SgrAttribute::NotOverline),
+            "25" => result.push(SgrAttribute::NotBlinking),
+            "51" => result.push(SgrAttribute::Framed),
+            "52" => result.push(SgrAttribute::Encircled),
+            "54" => result.push(SgrAttribute::NotFramedEncircled),
+            "39" => result.push(SgrAttribute::DefaultForeground),
+            "49" => result.push(SgrAttribute::DefaultBackground),
+            "59" => result.push(SgrAttribute::DefaultUnderlineColor),
             "30" => result.push(SgrAttribute::Foreground(Color::Black)),
             "31" => result.push(SgrAttribute::Foreground(Color::Red)),
             "32" => result.push(SgrAttribute::Foreground(Color::Green)),
@@ -288,15 +1267,15 @@ fn parse_sgr(params: &str) -> Vec<SgrAttribute> {
                 if let Some(next) = iter.next() {
                     if next == "5" {
                         // 8-bit color: 38;5;<n> or 48;5;<n> or 58;5;<n>
-                        if let Some(val) = iter.next() {
-                            if let Ok(idx) = val.parse::<u8>() {
-                                let color = Color::AnsiValue(idx);
-                                match color_type {
-                                    "38" => result.push(SgrAttribute::Foreground(color)),
-                                    "48" => result.push(SgrAttribute::Background(color)),
-                                    "58" => result.push(SgrAttribute::UnderlineColor(color)),
-                                    _ => {}
-                                }
+                        if let Some(val) = iter.next()
+                            && let Ok(idx) = val.parse::<u8>()
+                        {
+                            let color = Color::AnsiValue(idx);
+                            match color_type {
+                                "38" => result.push(SgrAttribute::Foreground(color)),
+                                "48" => result.push(SgrAttribute::Background(color)),
+                                "58" => result.push(SgrAttribute::UnderlineColor(color)),
+                                _ => {}
                             }
                         }
                     } else if next == "2" {
@@ -322,18 +1301,320 @@ fn parse_sgr(params: &str) -> Vec<SgrAttribute> {
     result
 }
 
-/// Parse cursor movement codes.
-fn parse_cursor(params: &str, final_byte: u8) -> Option<CursorMove> {
-    let n = params.parse::<u16>().unwrap_or(1);
-    match final_byte {
-        b'A' => Some(CursorMove::Up(n)),
+/// Parse a Linux console palette sequence at the start of `bytes`: `ESC ] P` followed
+/// by exactly seven hex digits (`n rr gg bb`), or `ESC ] R`.
+fn parse_linux_palette(bytes: &[u8]) -> Option<DeviceControl> {
+    let rest = bytes.strip_prefix(b"\x1B]")?;
+    match rest.first()? {
+        b'R' => Some(DeviceControl::LinuxPaletteReset),
+        b'P' => {
+            let digits = rest.get(1..8)?;
+            let hex = |i: usize| (digits[i] as char).to_digit(16).map(|d| d as u8);
+            let byte = |i: usize| Some(hex(i)? << 4 | hex(i + 1)?);
+            Some(DeviceControl::LinuxPalette {
+                index: hex(0)?,
+                r: byte(1)?,
+                g: byte(3)?,
+                b: byte(5)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Split an OSC string (`ESC ] body` terminated by BEL or `ESC \\`) at the start of `bytes`
+/// into its body and the total number of bytes consumed.
+fn parse_osc(bytes: &[u8]) -> Option<(&[u8], usize)> {
+    let rest = bytes.strip_prefix(b"\x1B]")?;
+    let end = rest.iter().position(|&b| b == 0x07 || b == 0x1B)?;
+    let terminator = match rest[end] {
+        0x07 => 1,
+        _ if rest.get(end + 1) == Some(&b'\\') => 2,
+        _ => return None,
+    };
+    Some((&rest[..end], 2 + end + terminator))
+}
+
+/// The length of a complete control string at the start of `bytes`: an OSC string
+/// (terminated by BEL or `ESC \\`) or a DCS, APC, PM or SOS string (terminated by `ESC \\`).
+fn control_string_len(bytes: &[u8]) -> Option<usize> {
+    if let Some((_, consumed)) = parse_osc(bytes) {
+        return Some(consumed);
+    }
+    if !matches!(bytes, [0x1B, b'P' | b'_' | b'^' | b'X', ..]) {
+        return None;
+    }
+    let end = bytes[2..].windows(2).position(|w| w == b"\x1B\\")?;
+    Some(2 + end + 2)
+}
+
+/// The length of an nF escape sequence at the start of `bytes`: ESC, one or more
+/// intermediate bytes (0x20-0x2F) and a final byte (0x30-0x7E).
+fn nf_escape_len(bytes: &[u8]) -> Option<usize> {
+    let rest = bytes.strip_prefix(b"\x1B")?;
+    let intermediates = rest
+        .iter()
+        .take_while(|b| (0x20..=0x2F).contains(*b))
+        .count();
+    let final_byte = *rest.get(intermediates)?;
+    (intermediates > 0 && (0x30..=0x7E).contains(&final_byte)).then_some(1 + intermediates + 1)
+}
+
+/// Parse the body of an OSC 133 shell integration sequence (`133;A` .. `133;D;code`).
+fn parse_prompt_mark(body: &[u8]) -> Option<PromptMark> {
+    let body = core::str::from_utf8(body.strip_prefix(b"133;")?).ok()?;
+    let mut parts = body.split(';');
+    match parts.next()? {
+        "A" => Some(PromptMark::PromptStart),
+        "B" => Some(PromptMark::CommandStart),
+        "C" => Some(PromptMark::CommandExecuted),
+        "D" => Some(PromptMark::CommandFinished(
+            parts.next().and_then(|code| code.parse().ok()),
+        )),
+        _ => None,
+    }
+}
+
+/// Parse one SGR parameter written with colon sub-parameters (ITU T.416 form).
+///
+/// Supports underline styles (`4:n`) and colors (`38:5:n`, `38:2:[cs]:r:g:b`, and the
+/// same for `48` and `58`). The colorspace id may be empty or left out entirely.
+fn parse_sgr_subparams(param: &str) -> Option<SgrAttribute> {
+    let parts: Vec<&str> = param.split(':').collect();
+    let byte = |v: &str| v.parse::<u8>().ok();
+    match parts[..] {
+        ["4", kind] => UnderlineKind::from_param(kind).map(SgrAttribute::UnderlineStyle),
+        [slot @ ("38" | "48" | "58"), ref rest @ ..] => {
+            let color = match rest {
+                ["5", idx] => Color::AnsiValue(byte(idx)?),
+                ["2", _, r, g, b] | ["2", r, g, b] => Color::Rgb24 {
+                    r: byte(r)?,
+                    g: byte(g)?,
+                    b: byte(b)?,
+                },
+                _ => return None,
+            };
+            Some(match slot {
+                "38" => SgrAttribute::Foreground(color),
+                "48" => SgrAttribute::Background(color),
+                _ => SgrAttribute::UnderlineColor(color),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Build the ratatui style for a set of SGR attributes.
+#[cfg(feature = "ratatui")]
+fn ratatui_style(codes: &[SgrAttribute]) -> ratatui::style::Style {
+    use ratatui::style::{Modifier, Style};
+
+    let mut style = Style::default();
+    for code in codes {
+        style = match *code {
+            SgrAttribute::Foreground(color) => style.fg(ratatui_color(color)),
+            SgrAttribute::Background(color) => style.bg(ratatui_color(color)),
+            SgrAttribute::Bold => style.add_modifier(Modifier::BOLD),
+            SgrAttribute::Faint => style.add_modifier(Modifier::DIM),
+            SgrAttribute::Italic => style.add_modifier(Modifier::ITALIC),
+            SgrAttribute::Underline => style.add_modifier(Modifier::UNDERLINED),
+            SgrAttribute::UnderlineStyle(kind) if kind != UnderlineKind::None => {
+                style.add_modifier(Modifier::UNDERLINED)
+            }
+            SgrAttribute::BlinkSlow => style.add_modifier(Modifier::SLOW_BLINK),
+            SgrAttribute::BlinkRapid => style.add_modifier(Modifier::RAPID_BLINK),
+            SgrAttribute::Reverse => style.add_modifier(Modifier::REVERSED),
+            SgrAttribute::Conceal => style.add_modifier(Modifier::HIDDEN),
+            SgrAttribute::CrossedOut => style.add_modifier(Modifier::CROSSED_OUT),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Map a color to ratatui's color type.
+///
+/// ratatui names ANSI white (7) `Gray` and bright white (15) `White`, and uses `Light*`
+/// for the other bright colors.
+#[cfg(feature = "ratatui")]
+fn ratatui_color(color: Color) -> ratatui::style::Color {
+    use ratatui::style::Color as Tui;
+
+    match color {
+        Color::Black => Tui::Black,
+        Color::Red => Tui::Red,
+        Color::Green => Tui::Green,
+        Color::Yellow => Tui::Yellow,
+        Color::Blue => Tui::Blue,
+        Color::Magenta => Tui::Magenta,
+        Color::Cyan => Tui::Cyan,
+        Color::White => Tui::Gray,
+        Color::BrightBlack => Tui::DarkGray,
+        Color::BrightRed => Tui::LightRed,
+        Color::BrightGreen => Tui::LightGreen,
+        Color::BrightYellow => Tui::LightYellow,
+        Color::BrightBlue => Tui::LightBlue,
+        Color::BrightMagenta => Tui::LightMagenta,
+        Color::BrightCyan => Tui::LightCyan,
+        Color::BrightWhite => Tui::White,
+        Color::AnsiValue(idx) => Tui::Indexed(idx),
+        Color::Rgb24 { r, g, b } => Tui::Rgb(r, g, b),
+    }
+}
+
+/// Map a color to the nearest index in IRC's 16-color palette.
+fn irc_color(color: Color) -> u8 {
+    match color.to_basic16() {
+        Color::Black => 1,
+        Color::Red => 5,
+        Color::Green => 3,
+        Color::Yellow => 7,
+        Color::Blue => 2,
+        Color::Magenta => 6,
+        Color::Cyan => 10,
+        Color::White => 15,
+        Color::BrightBlack => 14,
+        Color::BrightRed => 4,
+        Color::BrightGreen => 9,
+        Color::BrightYellow => 8,
+        Color::BrightBlue => 12,
+        Color::BrightMagenta => 13,
+        Color::BrightCyan => 11,
+        _ => 0,
+    }
+}
+
+/// Check that every SGR color parameter (`38`, `48`, `58`) is complete and in range.
+fn sgr_colors_valid(params: &str, separator: char) -> bool {
+    let is_u8 = |v: Option<&str>| v.is_some_and(|v| v.parse::<u8>().is_ok());
+    let mut iter = params.split(separator).filter(|s| !s.is_empty());
+    while let Some(param) = iter.next() {
+        if param.contains(':') && separator != ':' {
+            let is_color = matches!(param.split(':').next(), Some("38" | "48" | "58"));
+            if is_color && parse_sgr_subparams(param).is_none() {
+                return false;
+            }
+            continue;
+        }
+        if matches!(param, "38" | "48" | "58") {
+            let valid = match iter.next() {
+                Some("5") => is_u8(iter.next()),
+                Some("2") => is_u8(iter.next()) && is_u8(iter.next()) && is_u8(iter.next()),
+                _ => false,
+            };
+            if !valid {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Parse a numeric parameter, saturating at `u16::MAX` instead of failing on overflow.
+/// Leading zeros are allowed. Returns `None` for an empty or non-numeric parameter.
+fn parse_count(param: &str) -> Option<u16> {
+    if param.is_empty() || !param.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(param.bytes().fold(0u16, |n, b| {
+        n.saturating_mul(10).saturating_add(u16::from(b - b'0'))
+    }))
+}
+
+/// Parse cursor movement codes.
+fn parse_cursor(params: &str, final_byte: u8, separator: char) -> Option<CursorMove> {
+    let n = parse_count(params).unwrap_or(1);
+    match final_byte {
+        b'A' => Some(CursorMove::Up(n)),
         b'B' => Some(CursorMove::Down(n)),
         b'C' => Some(CursorMove::Forward(n)),
         b'D' => Some(CursorMove::Backward(n)),
         b'E' => Some(CursorMove::NextLine(n)),
         b'F' => Some(CursorMove::PreviousLine(n)),
         b'G' => Some(CursorMove::HorizontalAbsolute(n)),
+        b'd' => Some(CursorMove::VerticalAbsolute(n)),
+        b'e' => Some(CursorMove::VerticalRelative(n)),
         b'H' | b'f' => {
+            let mut split = params.split(separator);
+            let row = split.next().and_then(parse_count).unwrap_or(1);
+            let col = split.next().and_then(parse_count).unwrap_or(1);
+            Some(CursorMove::Position { row, col })
+        }
+        _ => None,
+    }
+}
+
+/// Parse tab stop movement and clearing codes (CHT, CBT, TBC).
+fn parse_tab(params: &str, final_byte: u8) -> Option<TabOp> {
+    match final_byte {
+        b'I' => Some(TabOp::Forward(parse_count(params).unwrap_or(1))),
+        b'Z' => Some(TabOp::Backward(parse_count(params).unwrap_or(1))),
+        b'g' => match params {
+            "" | "0" => Some(TabOp::Clear(TabClear::Current)),
+            "3" => Some(TabOp::Clear(TabClear::All)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parse scroll codes (`S`, `T`).
+///
+/// `T` with at most one parameter is scroll down; with five parameters it starts highlight
+/// mouse tracking instead.
+fn parse_scroll(params: &str, final_byte: u8) -> Option<AnsiEscape> {
+    let count = || match params {
+        "" => Some(1),
+        _ => parse_count(params),
+    };
+    match final_byte {
+        b'S' => Some(AnsiEscape::Scroll(Scroll::Up(count()?))),
+        b'T' => {
+            let values = params
+                .split(';')
+                .map(parse_count)
+                .collect::<Option<Vec<u16>>>();
+            match values.as_deref() {
+                Some(&[func, start_col, start_row, first_row, last_row]) => Some(
+                    AnsiEscape::Device(DeviceControl::InitiateHighlightMouseTracking {
+                        func,
+                        start_col,
+                        start_row,
+                        first_row,
+                        last_row,
+                    }),
+                ),
+                _ => Some(AnsiEscape::Scroll(Scroll::Down(count()?))),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse SGR mouse events (e.g., "<0;15;8" with final byte 'M' or 'm').
+fn parse_mouse(params: &str, final_byte: u8) -> Option<AnsiEscape> {
+    let pressed = match final_byte {
+        b'M' => true,
+        b'm' => false,
+        _ => return None,
+    };
+    let mut split = params.strip_prefix('<')?.split(';');
+    let button = split.next()?.parse::<u16>().ok()?;
+    let col = split.next()?.parse::<u16>().ok()?;
+    let row = split.next()?.parse::<u16>().ok()?;
+    Some(AnsiEscape::Mouse {
+        button,
+        col,
+        row,
+        pressed,
+    })
+}
+
+/// Parse terminal replies and input markers (cursor position and window reports, focus
+/// events, bracketed paste markers).
+fn parse_report(params: &str, final_byte: u8) -> Option<AnsiEscape> {
+    match final_byte {
+        b'R' => {
             let mut split = params.split(';');
             let row = split
                 .next()
@@ -343,8 +1624,19 @@ fn parse_cursor(params: &str, final_byte: u8) -> Option<CursorMove> {
                 .next()
                 .and_then(|v| v.parse::<u16>().ok())
                 .unwrap_or(1);
-            Some(CursorMove::Position { row, col })
+            Some(AnsiEscape::CursorPositionReport { row, col })
         }
+        b't' => {
+            let mut split = params.split(';').map(|v| v.parse::<u16>().ok());
+            let kind = split.next()??;
+            let values = split.collect::<Option<Vec<u16>>>()?;
+            Some(AnsiEscape::WindowReport { kind, values })
+        }
+        b'I' if params.is_empty() => Some(AnsiEscape::Focus { gained: true }),
+        b'O' if params.is_empty() => Some(AnsiEscape::Focus { gained: false }),
+        b'~' if params == "200" => Some(AnsiEscape::PasteStart),
+        b'~' if params == "201" => Some(AnsiEscape::PasteEnd),
+        b'~' => params.parse().ok().map(AnsiEscape::FunctionKey),
         _ => None,
     }
 }
@@ -364,19 +1656,132 @@ fn parse_erase(params: &str, final_byte: u8) -> Option<Erase> {
     }
 }
 
-/// Parse device control codes (save/restore cursor, hide/show cursor).
+/// Parse device control codes (save/restore cursor, cursor style, Kitty keyboard protocol).
 fn parse_device(params: &str, final_byte: u8) -> Option<DeviceControl> {
+    // The Kitty keyboard protocol shares the 'u' final byte with RestoreCursor
+    if final_byte == b'u' {
+        if let Some(flags) = params.strip_prefix('>') {
+            let flags = KittyKeyboardFlags::from_bits(flags.parse().unwrap_or(0));
+            return Some(DeviceControl::SetKittyKeyboard(flags));
+        }
+        if let Some(count) = params.strip_prefix('<') {
+            return Some(DeviceControl::PopKittyKeyboard(count.parse().unwrap_or(1)));
+        }
+    }
+    // DECSCUSR: the ' ' intermediate byte is kept at the end of the params
+    if final_byte == b'q'
+        && let Some(style) = params.strip_suffix(' ')
+    {
+        let style = if style.is_empty() {
+            0
+        } else {
+            style.parse().ok()?
+        };
+        return CursorStyle::from_param(style).map(DeviceControl::SetCursorStyle);
+    }
     match (params, final_byte) {
         ("", b's') => Some(DeviceControl::SaveCursor),
         ("", b'u') => Some(DeviceControl::RestoreCursor),
-        ("?25l", b'l') => Some(DeviceControl::HideCursor),
-        ("?25h", b'h') => Some(DeviceControl::ShowCursor),
-        ("?25", b'l') => Some(DeviceControl::HideCursor),
-        ("?25", b'h') => Some(DeviceControl::ShowCursor),
+        // DECSTR: '!' is an intermediate byte, kept in the params by the scanner
+        ("!", b'p') => Some(DeviceControl::SoftReset),
         _ => None,
     }
 }
 
+/// Parse DEC private mode set/reset (e.g., "?1049" with final byte 'h' or 'l').
+/// Modes may be combined in one sequence (e.g. "?12;25h"), so this can yield several codes.
+/// Cursor visibility (25), blinking (12), and the alternate screen (1049) map to their
+/// [`DeviceControl`] variants.
+fn parse_private_modes(params: &str, final_byte: u8) -> Option<Vec<AnsiEscape>> {
+    let enabled = match final_byte {
+        b'h' => true,
+        b'l' => false,
+        _ => return None,
+    };
+    let modes = params.strip_prefix('?')?;
+    let escapes = modes
+        .split(';')
+        .filter_map(|mode| mode.parse::<u16>().ok())
+        .map(|mode| match (mode, enabled) {
+            (25, false) => AnsiEscape::Device(DeviceControl::HideCursor),
+            (25, true) => AnsiEscape::Device(DeviceControl::ShowCursor),
+            (12, _) => AnsiEscape::Device(DeviceControl::SetCursorBlink(enabled)),
+            (1049, true) => AnsiEscape::Device(DeviceControl::EnterAltScreen),
+            (1049, false) => AnsiEscape::Device(DeviceControl::ExitAltScreen),
+            _ => match AltScreenMode::from_mode(mode) {
+                Some(mode) => AnsiEscape::Device(DeviceControl::AltScreen { mode, enabled }),
+                None => AnsiEscape::PrivateMode { mode, enabled },
+            },
+        })
+        .collect();
+    Some(escapes)
+}
+
+/// An event produced by [`AnsiEvents`]: either a run of plain text or a single escape code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// A run of text containing no escape codes, borrowed from the input.
+    Text(&'a str),
+    /// A single parsed escape code.
+    Escape(AnsiEscape),
+}
+
+/// Lazy iterator over the [`Event`]s in a string, returned by [`AnsiParser::events`].
+pub struct AnsiEvents<'a> {
+    parser: AnsiParser<'a>,
+    pending: alloc::vec::IntoIter<AnsiEscape>,
+}
+
+impl<'a> AnsiEvents<'a> {
+    /// Internal: byte offset in the input up to which events have been produced.
+    pub(crate) fn offset(&self) -> usize {
+        self.parser.pos
+    }
+
+    /// Internal: like [`Iterator::next`], but yields `Some(None)` for a sequence that
+    /// produces no event (e.g. an OSC 8 hyperlink) instead of skipping it. The sequence
+    /// is the input between [`offset`](Self::offset) before and after the call.
+    pub(crate) fn next_or_skip(&mut self) -> Option<Option<Event<'a>>> {
+        // Drain codes left over from a multi-code sequence first
+        if let Some(escape) = self.pending.next() {
+            return Some(Some(Event::Escape(escape)));
+        }
+        let input = self.parser.input;
+        if self.parser.pos >= input.len() {
+            return None;
+        }
+        if let Some((escapes, consumed)) = self.parser.parse_next_escapes() {
+            self.parser.pos += consumed;
+            let mut escapes = escapes.into_iter();
+            let first = escapes.next();
+            self.pending = escapes;
+            return Some(first.map(Event::Escape));
+        }
+        // Collect a run of text up to the next escape sequence
+        let start = self.parser.pos;
+        loop {
+            self.parser.pos = self.parser.next_escape_candidate();
+            if self.parser.pos >= input.len() || self.parser.parse_next_escapes().is_some() {
+                break;
+            }
+        }
+        Some(Some(Event::Text(&input[start..self.parser.pos])))
+    }
+}
+
+impl<'a> Iterator for AnsiEvents<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Unknown or malformed sequences produce no event
+            if let Some(event) = self.next_or_skip()? {
+                return Some(event);
+            }
+        }
+    }
+}
+
 /// Convenience function for one-shot annotated parsing.
 /// Convenience function to parse a string for ANSI escape codes and return an annotated result.
 ///
@@ -389,22 +1794,381 @@ pub fn parse_ansi_annotated(input: &str) -> AnsiParseResult {
     AnsiParser::new(input).parse_annotated()
 }
 
+/// Like [`parse_ansi_annotated`], with explicit [`ParseOptions`].
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::{ParseOptions, parse_ansi_annotated_with};
+/// let mut options = ParseOptions::default();
+/// options.strip_lone_esc = true;
+/// assert_eq!(parse_ansi_annotated_with("\x1Bx", &options).text, "x");
+/// ```
+pub fn parse_ansi_annotated_with(input: &str, options: &ParseOptions) -> AnsiParseResult {
+    AnsiParser::new(input)
+        .with_options(*options)
+        .parse_annotated()
+}
+
+/// Count the `char`s in the text of `input` once escape codes are removed, without
+/// building a full [`AnsiParseResult`].
+///
+/// This is the same as `parse_ansi_annotated(input).text.chars().count()`. It counts
+/// chars, not terminal columns: wide characters count once.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::visible_len;
+/// assert_eq!(visible_len("\x1B[1;31mh\u{e9}llo\x1B[0m"), 5);
+/// ```
+pub fn visible_len(input: &str) -> usize {
+    let mut len = 0;
+    for event in AnsiParser::new(input).events() {
+        match event {
+            Event::Text(text) => len += text.chars().count(),
+            // REP repeats the preceding character, if there is one
+            Event::Escape(AnsiEscape::Repeat(count)) if len > 0 => len += count as usize,
+            Event::Escape(_) => {}
+        }
+    }
+    len
+}
+
+/// Remove escape codes from `input`, borrowing it when there are none to remove.
+///
+/// The result is the same text as `parse_ansi_annotated(input).text`, but input without
+/// any ESC byte or C1 CSI character is returned as [`Cow::Borrowed`] without allocating.
+///
+/// # Example
+/// ```
+/// use std::borrow::Cow;
+/// use ansi_escapers::interpreter::strip_ansi_cow;
+/// assert!(matches!(strip_ansi_cow("plain"), Cow::Borrowed("plain")));
+/// assert_eq!(strip_ansi_cow("\x1B[31mred\x1B[0m"), "red");
+/// ```
+pub fn strip_ansi_cow(input: &str) -> Cow<'_, str> {
+    let bytes = input.as_bytes();
+    let has_escape = memchr(0x1B, bytes).is_some() || input.contains('\u{9B}');
+    if has_escape {
+        Cow::Owned(AnsiParser::new(input).parse_annotated().text)
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Write `input` with its escape codes removed to `out`.
+///
+/// Writes the same text as `parse_ansi_annotated(input).text`, copying each run of plain
+/// text straight from `input` instead of building the cleaned string first, so large
+/// inputs can be piped through with little memory.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::strip_ansi_to;
+/// let mut out = Vec::new();
+/// strip_ansi_to("\x1B[1mbold\x1B[0m text", &mut out)?;
+/// assert_eq!(out, b"bold text");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn strip_ansi_to<W: std::io::Write>(input: &str, out: &mut W) -> std::io::Result<()> {
+    let mut last = None;
+    for event in AnsiParser::new(input).events() {
+        match event {
+            Event::Text(text) => {
+                out.write_all(text.as_bytes())?;
+                last = text.chars().next_back().or(last);
+            }
+            // REP repeats the preceding character, if there is one
+            Event::Escape(AnsiEscape::Repeat(count)) => {
+                if let Some(ch) = last {
+                    let mut buf = [0; 4];
+                    let encoded = ch.encode_utf8(&mut buf).as_bytes();
+                    for _ in 0..count {
+                        out.write_all(encoded)?;
+                    }
+                }
+            }
+            Event::Escape(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parse the key sequence at the start of `input`, as read from a terminal in raw mode.
+///
+/// Returns the key and the number of bytes it used, or `None` if `input` does not start
+/// with a recognized key sequence. Both the normal (`ESC [ A`) and application mode
+/// (`ESC O A`) forms of the cursor keys are accepted, as are the `ESC [ n ~` keys.
+/// A modifier parameter (e.g. `ESC [ 1 ; 5 C` for Ctrl+Right) is accepted; use
+/// [`parse_key_with_modifiers`] to also find out which modifiers were held.
+///
+/// This is input-oriented: `ESC [ A` is the Up key here, while [`AnsiParser`] reads the
+/// same bytes as a cursor movement in output.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::Key;
+/// use ansi_escapers::interpreter::parse_key;
+/// assert_eq!(parse_key("\x1B[3~rest"), Some((Key::Delete, 4)));
+/// assert_eq!(parse_key("\x1B[1;5C"), Some((Key::Right, 6)));
+/// assert_eq!(parse_key("plain"), None);
+/// ```
+pub fn parse_key(input: &str) -> Option<(Key, usize)> {
+    parse_key_with_modifiers(input).map(|(key, _, len)| (key, len))
+}
+
+/// Like [`parse_key`], but also return the modifiers held with the key.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::{Key, Modifiers};
+/// use ansi_escapers::interpreter::parse_key_with_modifiers;
+/// let (key, mods, _) = parse_key_with_modifiers("\x1B[1;5C").unwrap();
+/// assert_eq!(key, Key::Right);
+/// assert!(mods.ctrl && !mods.shift);
+/// ```
+pub fn parse_key_with_modifiers(input: &str) -> Option<(Key, Modifiers, usize)> {
+    let bytes = input.as_bytes();
+    // SS3 form, sent for cursor keys in application mode and for F1-F4
+    if let [0x1B, b'O', final_byte, ..] = *bytes {
+        let key = match final_byte {
+            b'P' => Key::F(1),
+            b'Q' => Key::F(2),
+            b'R' => Key::F(3),
+            b'S' => Key::F(4),
+            _ => letter_key(final_byte)?,
+        };
+        return Some((key, Modifiers::default(), 3));
+    }
+    let rest = bytes.strip_prefix(b"\x1B[")?;
+    let end = rest.iter().position(|b| (0x40..=0x7E).contains(b))?;
+    let params = core::str::from_utf8(&rest[..end]).ok()?;
+    let mut fields = params.split(';');
+    let number = fields.next().unwrap_or_default();
+    let modifier = match fields.next() {
+        Some(m) => Some(m.parse::<u16>().ok()?),
+        None => None,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    let key = match rest[end] {
+        b'~' => match number.parse::<u8>().ok()? {
+            1 | 7 => Key::Home,
+            2 => Key::Insert,
+            3 => Key::Delete,
+            4 | 8 => Key::End,
+            5 => Key::PageUp,
+            6 => Key::PageDown,
+            // F5-F12 skip 16 and 22
+            n @ 11..=15 => Key::F(n - 10),
+            n @ 17..=21 => Key::F(n - 11),
+            n @ 23..=24 => Key::F(n - 12),
+            _ => return None,
+        },
+        // Modified F1, F2 and F4 (`ESC [ 1 ; 5 P`). F3 would be `ESC [ 1 ; m R`, which
+        // cannot be told apart from a cursor position report, so it is not accepted.
+        b'P' | b'Q' | b'S' if number == "1" && modifier.is_some() => Key::F(rest[end] - b'P' + 1),
+        final_byte if matches!(number, "" | "1") => letter_key(final_byte)?,
+        _ => return None,
+    };
+    let modifiers = modifier.map(Modifiers::from_param).unwrap_or_default();
+    Some((key, modifiers, end + 3))
+}
+
+/// The key for the final byte of a cursor key sequence (`ESC [ A`, `ESC O H`, ...).
+fn letter_key(final_byte: u8) -> Option<Key> {
+    match final_byte {
+        b'A' => Some(Key::Up),
+        b'B' => Some(Key::Down),
+        b'C' => Some(Key::Right),
+        b'D' => Some(Key::Left),
+        b'H' => Some(Key::Home),
+        b'F' => Some(Key::End),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ansi_escape::ansi_creator::{AnsiCreator, AnsiEnvironment};
     use crate::ansi_escape::ansi_types::*;
 
+    fn creator() -> AnsiCreator {
+        AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        })
+    }
+
+    #[test]
+    fn test_parse_key_arrows() {
+        assert_eq!(parse_key("\x1B[A"), Some((Key::Up, 3)));
+        assert_eq!(parse_key("\x1B[B"), Some((Key::Down, 3)));
+        assert_eq!(parse_key("\x1BOC"), Some((Key::Right, 3)));
+        assert_eq!(parse_key("\x1BOD"), Some((Key::Left, 3)));
+        assert_eq!(parse_key("\x1B[1;5C"), Some((Key::Right, 6)));
+        assert_eq!(parse_key("\x1B[1;2A\x1B[B"), Some((Key::Up, 6)));
+        // A count is a cursor movement, not a key
+        assert_eq!(parse_key("\x1B[3A"), None);
+    }
+
+    #[test]
+    fn test_strip_ansi_cow_borrows_clean_input() {
+        assert!(matches!(
+            strip_ansi_cow("plain text"),
+            Cow::Borrowed("plain text")
+        ));
+        assert!(matches!(strip_ansi_cow(""), Cow::Borrowed("")));
+        let stripped = strip_ansi_cow("\x1B[31mx\x1B[0m");
+        assert!(matches!(stripped, Cow::Owned(_)));
+        assert_eq!(stripped, "x");
+        assert_eq!(strip_ansi_cow("a\u{9B}1mb"), "ab");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_strip_ansi_to_matches_cow() {
+        for input in [
+            "",
+            "plain",
+            "\x1B[1;31mred\x1B[0m and \x1B]0;title\x07plain",
+            "x\x1B[3b\x1B[2Jy\u{9B}4m\u{e9}",
+            "\x1B[5bnothing before",
+        ] {
+            let mut out = Vec::new();
+            strip_ansi_to(input, &mut out).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), strip_ansi_cow(input));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_strip_ansi_to_propagates_errors() {
+        struct Failing;
+        impl std::io::Write for Failing {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("closed"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = strip_ansi_to("\x1B[1mtext", &mut Failing).unwrap_err();
+        assert_eq!(err.to_string(), "closed");
+        // Nothing is written for input without text
+        assert!(strip_ansi_to("\x1B[1m\x1B[0m", &mut Failing).is_ok());
+    }
+
+    #[test]
+    fn test_parse_key_modifiers() {
+        let mods = |input| parse_key_with_modifiers(input).map(|(key, mods, _)| (key, mods));
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+        let ctrl_alt = Modifiers {
+            ctrl: true,
+            alt: true,
+            ..Modifiers::default()
+        };
+        assert_eq!(mods("\x1B[1;2A"), Some((Key::Up, shift)));
+        assert_eq!(mods("\x1B[1;5C"), Some((Key::Right, ctrl)));
+        assert_eq!(mods("\x1B[1;7D"), Some((Key::Left, ctrl_alt)));
+        assert_eq!(mods("\x1B[3;5~"), Some((Key::Delete, ctrl)));
+        assert_eq!(mods("\x1B[D"), Some((Key::Left, Modifiers::default())));
+        assert_eq!(mods("\x1B[1;xD"), None);
+    }
+
+    #[test]
+    fn test_parse_key_tilde_and_function_keys() {
+        assert_eq!(parse_key("\x1B[1~"), Some((Key::Home, 4)));
+        assert_eq!(parse_key("\x1B[3~"), Some((Key::Delete, 4)));
+        assert_eq!(parse_key("\x1B[5~"), Some((Key::PageUp, 4)));
+        assert_eq!(parse_key("\x1B[6;3~"), Some((Key::PageDown, 6)));
+        assert_eq!(parse_key("\x1BOP"), Some((Key::F(1), 3)));
+        assert_eq!(parse_key("\x1B[1;5Q"), Some((Key::F(2), 6)));
+        assert_eq!(parse_key("\x1B[15~"), Some((Key::F(5), 5)));
+        assert_eq!(parse_key("\x1B[24~"), Some((Key::F(12), 5)));
+        assert_eq!(parse_key("\x1B[16~"), None);
+        assert_eq!(parse_key("\x1B[200~"), None);
+        assert_eq!(parse_key("\x1B["), None);
+        assert_eq!(parse_key(""), None);
+    }
+
+    #[cfg(feature = "ratatui")]
+    #[test]
+    fn test_into_ratatui_text_bold_red() {
+        use ratatui::style::{Color as Tui, Modifier, Style};
+        use ratatui::text::Span;
+
+        let text = parse_ansi_annotated("\x1B[1;31mhot\x1B[0m cold\r\n\x1B[48;5;17mnext")
+            .into_ratatui_text();
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(
+            text.lines[0].spans,
+            vec![
+                Span::styled(
+                    "hot",
+                    Style::new().fg(Tui::Red).add_modifier(Modifier::BOLD)
+                ),
+                Span::raw(" cold"),
+            ]
+        );
+        assert_eq!(
+            text.lines[1].spans,
+            vec![Span::styled("next", Style::new().bg(Tui::Indexed(17)))]
+        );
+    }
+
+    #[test]
+    fn test_effective_colors() {
+        let colors = |input| parse_ansi_annotated(input).spans[0].effective_colors();
+        assert_eq!(
+            colors("\x1B[32;44mx"),
+            (Some(Color::Green), Some(Color::Blue))
+        );
+        // Reverse swaps foreground and background
+        assert_eq!(
+            colors("\x1B[32;44;7mx"),
+            (Some(Color::Blue), Some(Color::Green))
+        );
+        // Conceal paints the text in the background color
+        assert_eq!(
+            colors("\x1B[32;44;8mx"),
+            (Some(Color::Blue), Some(Color::Blue))
+        );
+        assert_eq!(colors("\x1B[1mx"), (None, None));
+        let span = AnsiSpan {
+            start: 0,
+            end: 1,
+            codes: vec![
+                SgrAttribute::Foreground(Color::Red),
+                SgrAttribute::DefaultForeground,
+                SgrAttribute::Background(Color::Red),
+            ],
+        };
+        assert_eq!(span.effective_colors(), (None, Some(Color::Red)));
+    }
+
     #[test]
     fn test_parser_sgr_and_cursor() {
         let input = "A\x1B[31mB\x1B[0mC\x1B[2J";
         let result = parse_ansi_annotated(input);
         assert_eq!(result.text, "ABC");
-        // SGR and erase/cursor codes should be detected as points (span logic not yet implemented)
-        assert!(
-            result
-                .points
-                .iter()
-                .any(|p| matches!(p.code, AnsiEscape::Sgr(_)))
+        // SGR codes are reported as spans, erase/cursor codes as points
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 1,
+                end: 2,
+                codes: vec![SgrAttribute::Foreground(Color::Red)],
+            }]
         );
         assert!(
             result
@@ -419,19 +2183,14 @@ mod tests {
         let input = "X\x1B[31mY\x1B[0mZ";
         let result = parse_ansi_annotated(input);
         assert_eq!(result.text, "XYZ");
-        let sgr_points: Vec<_> = result
-            .points
-            .iter()
-            .filter_map(|p| {
-                if let AnsiEscape::Sgr(attr) = p.code {
-                    Some(attr)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        assert!(sgr_points.contains(&SgrAttribute::Foreground(Color::Red)));
-        assert!(sgr_points.contains(&SgrAttribute::Reset));
+        assert_eq!(result.spans.len(), 1);
+        assert!(
+            result.spans[0]
+                .codes
+                .contains(&SgrAttribute::Foreground(Color::Red))
+        );
+        // The reset closes the span before "Z"
+        assert_eq!((result.spans[0].start, result.spans[0].end), (1, 2));
     }
 
     #[test]
@@ -439,19 +2198,13 @@ mod tests {
         let input = "A\x1B[38;5;123mB\x1B[0m";
         let result = parse_ansi_annotated(input);
         assert_eq!(result.text, "AB");
-        let sgr_points: Vec<_> = result
-            .points
-            .iter()
-            .filter_map(|p| {
-                if let AnsiEscape::Sgr(attr) = p.code {
-                    Some(attr)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        assert!(sgr_points.contains(&SgrAttribute::Foreground(Color::AnsiValue(123))));
-        assert!(sgr_points.contains(&SgrAttribute::Reset));
+        assert_eq!(result.spans.len(), 1);
+        assert!(
+            result.spans[0]
+                .codes
+                .contains(&SgrAttribute::Foreground(Color::AnsiValue(123)))
+        );
+        assert_eq!((result.spans[0].start, result.spans[0].end), (1, 2));
     }
 
     #[test]
@@ -462,8 +2215,8 @@ mod tests {
         let mut fg = false;
         let mut bg = false;
         let mut ul = false;
-        for p in &result.points {
-            if let AnsiEscape::Sgr(attr) = p.code {
+        for span in &result.spans {
+            for attr in &span.codes {
                 match attr {
                     SgrAttribute::Foreground(Color::Rgb24 {
                         r: 10,
@@ -490,62 +2243,1741 @@ mod tests {
     }
 
     #[test]
-    fn test_parser_cursor_movement() {
-        let input = "A\x1B[2BC";
-        let result = parse_ansi_annotated(input);
-        assert_eq!(result.text, "AC");
-        let found = result
-            .points
-            .iter()
-            .any(|p| matches!(p.code, AnsiEscape::Cursor(CursorMove::Down(2))));
-        assert!(found, "Did not find CursorMove::Down(2)");
+    fn test_parser_24bit_color_with_colorspace_id() {
+        let rgb = Color::Rgb24 {
+            r: 10,
+            g: 20,
+            b: 30,
+        };
+        // A colorspace id is only read in the colon form
+        for input in [
+            "\x1B[38;2;10;20;30mx",
+            "\x1B[38:2:0:10:20:30mx",
+            "\x1B[38:2::10:20:30mx",
+        ] {
+            let result = parse_ansi_annotated(input);
+            assert_eq!(
+                result.spans[0].codes,
+                vec![SgrAttribute::Foreground(rgb)],
+                "{:?}",
+                input
+            );
+        }
     }
 
     #[test]
-    fn test_parser_erase_display_and_line() {
-        let input = "A\x1B[2JB\x1B[1KC";
-        let result = parse_ansi_annotated(input);
-        assert_eq!(result.text, "ABC");
-        let found_display = result
-            .points
-            .iter()
-            .any(|p| matches!(p.code, AnsiEscape::Erase(Erase::Display(EraseMode::All))));
-        let found_line = result
-            .points
-            .iter()
-            .any(|p| matches!(p.code, AnsiEscape::Erase(Erase::Line(EraseMode::ToStart))));
-        assert!(found_display, "Did not find Erase::Display(EraseMode::All)");
-        assert!(found_line, "Did not find Erase::Line(EraseMode::ToStart)");
+    fn test_parser_24bit_color_followed_by_attribute() {
+        let rgb = Color::Rgb24 {
+            r: 10,
+            g: 20,
+            b: 30,
+        };
+        // The semicolon form is always `r;g;b`, so a single attribute after it is kept
+        let result = parse_ansi_annotated("\x1B[38;2;10;20;30;1mx");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Bold, SgrAttribute::Foreground(rgb)]
+        );
+        let result = parse_ansi_annotated("\x1B[48;2;10;20;30;4mx");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Underline, SgrAttribute::Background(rgb)]
+        );
+        let result = parse_ansi_annotated("\x1B[38;2;10;20;30;1;4mx");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![
+                SgrAttribute::Bold,
+                SgrAttribute::Underline,
+                SgrAttribute::Foreground(Color::Rgb24 {
+                    r: 10,
+                    g: 20,
+                    b: 30
+                }),
+            ]
+        );
     }
 
     #[test]
-    fn test_parser_device_control() {
-        let input = "A\x1B[sB\x1B[uC\x1B[?25lD\x1B[?25hE";
-        let result = parse_ansi_annotated(input);
-        assert_eq!(result.text, "ABCDE");
-        let mut save = false;
-        let mut restore = false;
-        let mut hide = false;
-        let mut show = false;
-        for p in &result.points {
-            match p.code {
-                AnsiEscape::Device(DeviceControl::SaveCursor) => save = true,
-                AnsiEscape::Device(DeviceControl::RestoreCursor) => restore = true,
-                AnsiEscape::Device(DeviceControl::HideCursor) => hide = true,
-                AnsiEscape::Device(DeviceControl::ShowCursor) => show = true,
-                _ => {}
-            }
-        }
-        assert!(save, "Did not find DeviceControl::SaveCursor");
-        assert!(restore, "Did not find DeviceControl::RestoreCursor");
+    fn test_parser_colon_underline_style() {
+        let result = parse_ansi_annotated("\x1B[4:3mtypo\x1B[0m");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::UnderlineStyle(UnderlineKind::Curly)]
+        );
+    }
+
+    #[test]
+    fn test_parser_underline_style_round_trip() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        });
+        for kind in [
+            UnderlineKind::None,
+            UnderlineKind::Straight,
+            UnderlineKind::Double,
+            UnderlineKind::Curly,
+            UnderlineKind::Dotted,
+            UnderlineKind::Dashed,
+        ] {
+            let attr = SgrAttribute::UnderlineStyle(kind);
+            let code = creator.sgr_code(attr);
+            assert_eq!(code, format!("\x1B[4:{}m", kind.param()));
+            let result = parse_ansi_annotated(&format!("{}x", code));
+            if kind == UnderlineKind::None {
+                // `4:0` turns underline off, so it styles nothing
+                assert!(result.spans.is_empty());
+            } else {
+                assert_eq!(result.spans[0].codes, vec![attr], "{:?}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parser_underline_none_ends_underline() {
+        let result = parse_ansi_annotated("\x1B[4;31ma\x1B[4:0mb\x1B[4:3mc\x1B[4:0md");
+        let codes: Vec<_> = result.spans.iter().map(|s| s.codes.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                vec![
+                    SgrAttribute::Underline,
+                    SgrAttribute::Foreground(Color::Red)
+                ],
+                vec![SgrAttribute::Foreground(Color::Red)],
+                vec![
+                    SgrAttribute::Foreground(Color::Red),
+                    SgrAttribute::UnderlineStyle(UnderlineKind::Curly),
+                ],
+                vec![SgrAttribute::Foreground(Color::Red)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_underline_style_replaces_underline() {
+        let result = parse_ansi_annotated("\x1B[4ma\x1B[4:3mb\x1B[4mc");
+        let codes: Vec<_> = result.spans.iter().map(|s| s.codes.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                vec![SgrAttribute::Underline],
+                vec![SgrAttribute::UnderlineStyle(UnderlineKind::Curly)],
+                vec![SgrAttribute::Underline],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_colon_only_sgr_is_ignored() {
+        for input in ["a\x1B[:mb", "a\x1B[38:mb", "a\x1B[::mb", "a\x1B[58::mb"] {
+            let result = parse_ansi_annotated(input);
+            assert_eq!(result.text, "ab", "{:?}", input);
+            assert_eq!(result.spans, vec![], "{:?}", input);
+            assert_eq!(result.points, vec![], "{:?}", input);
+            let result = AnsiParser::new(input)
+                .with_sgr_all_colon(true)
+                .parse_annotated();
+            assert_eq!(result.spans, vec![], "{:?}", input);
+        }
+        // The valid attributes around a malformed token are kept
+        let result = parse_ansi_annotated("\x1B[1;38:;4mx");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Bold, SgrAttribute::Underline]
+        );
+    }
+
+    #[test]
+    fn test_parser_sgr_all_colon() {
+        let parse = |input| {
+            AnsiParser::new(input)
+                .with_sgr_all_colon(true)
+                .parse_annotated()
+                .spans[0]
+                .codes
+                .clone()
+        };
+        assert_eq!(
+            parse("\x1B[1:31:4mx"),
+            vec![
+                SgrAttribute::Bold,
+                SgrAttribute::Underline,
+                SgrAttribute::Foreground(Color::Red),
+            ]
+        );
+        let red = SgrAttribute::Foreground(Color::Rgb24 { r: 255, g: 0, b: 0 });
+        assert_eq!(parse("\x1B[38:2::255:0:0mx"), vec![red]);
+        assert_eq!(
+            parse("\x1B[1;38:5:9mx"),
+            vec![
+                SgrAttribute::Bold,
+                SgrAttribute::Foreground(Color::AnsiValue(9)),
+            ]
+        );
+        // Colors and underline styles keep their sub-parameters
+        assert_eq!(parse("\x1B[38:2:0:255:0:0mx"), vec![red]);
+        assert_eq!(
+            parse("\x1B[4:3mx"),
+            vec![SgrAttribute::UnderlineStyle(UnderlineKind::Curly)]
+        );
+        assert_eq!(
+            parse("\x1B[1:3;48:5:9mx"),
+            vec![
+                SgrAttribute::Bold,
+                SgrAttribute::Italic,
+                SgrAttribute::Background(Color::AnsiValue(9)),
+            ]
+        );
+        // Without the option, `4:3` is still a sub-parameter
+        assert_eq!(
+            parse_ansi_annotated("\x1B[4:3mx").spans[0].codes,
+            vec![SgrAttribute::UnderlineStyle(UnderlineKind::Curly)]
+        );
+    }
+
+    #[test]
+    fn test_parser_colon_truecolor() {
+        let red = Color::Rgb24 { r: 255, g: 0, b: 0 };
+        for input in [
+            "\x1B[38:2::255:0:0mx",
+            "\x1B[38:2:0:255:0:0mx",
+            "\x1B[38:2:255:0:0mx",
+            "\x1B[38;2;255;0;0mx",
+        ] {
+            let result = parse_ansi_annotated(input);
+            assert_eq!(
+                result.spans[0].codes,
+                vec![SgrAttribute::Foreground(red)],
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parser_colon_mixed_with_semicolons() {
+        let result = parse_ansi_annotated("\x1B[1;58:5:196;4:2mx");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![
+                SgrAttribute::Bold,
+                SgrAttribute::UnderlineColor(Color::AnsiValue(196)),
+                SgrAttribute::UnderlineStyle(UnderlineKind::Double),
+            ]
+        );
+        let err = AnsiParser::new("\x1B[38:2:1:2m")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(err.kind, AnsiParseErrorKind::BadColorParam);
+    }
+
+    #[test]
+    fn test_parser_cursor_movement() {
+        let input = "A\x1B[2BC";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "AC");
+        let found = result
+            .points
+            .iter()
+            .any(|p| matches!(p.code, AnsiEscape::Cursor(CursorMove::Down(2))));
+        assert!(found, "Did not find CursorMove::Down(2)");
+    }
+
+    #[test]
+    fn test_parser_counts_leading_zeros_and_saturation() {
+        let codes: Vec<_> =
+            parse_ansi_annotated("\x1B[007A\x1B[99999A\x1B[0099999;070H\x1B[70000Z")
+                .points
+                .into_iter()
+                .map(|p| p.code)
+                .collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Cursor(CursorMove::Up(7)),
+                AnsiEscape::Cursor(CursorMove::Up(65535)),
+                AnsiEscape::Cursor(CursorMove::Position {
+                    row: 65535,
+                    col: 70
+                }),
+                AnsiEscape::Tab(TabOp::Backward(65535)),
+            ]
+        );
+        // Non-numeric counts still fall back to the default
+        assert_eq!(
+            parse_ansi_annotated("\x1B[;A").points[0].code,
+            AnsiEscape::Cursor(CursorMove::Up(1))
+        );
+    }
+
+    #[test]
+    fn test_parser_vertical_position_round_trip() {
+        let creator = creator();
+        for (input, expected) in [
+            ("\x1B[5d", CursorMove::VerticalAbsolute(5)),
+            ("\x1B[2e", CursorMove::VerticalRelative(2)),
+        ] {
+            let result = parse_ansi_annotated(input);
+            assert_eq!(result.points.len(), 1);
+            assert_eq!(result.points[0].code, AnsiEscape::Cursor(expected));
+            assert_eq!(creator.cursor_code(expected), input);
+        }
+    }
+
+    #[test]
+    fn test_parser_vertical_position_defaults_to_one() {
+        let result = parse_ansi_annotated("\x1B[d\x1B[e");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Cursor(CursorMove::VerticalAbsolute(1)),
+                AnsiEscape::Cursor(CursorMove::VerticalRelative(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_erase_display_and_line() {
+        let input = "A\x1B[2JB\x1B[1KC";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "ABC");
+        let found_display = result
+            .points
+            .iter()
+            .any(|p| matches!(p.code, AnsiEscape::Erase(Erase::Display(EraseMode::All))));
+        let found_line = result
+            .points
+            .iter()
+            .any(|p| matches!(p.code, AnsiEscape::Erase(Erase::Line(EraseMode::ToStart))));
+        assert!(found_display, "Did not find Erase::Display(EraseMode::All)");
+        assert!(found_line, "Did not find Erase::Line(EraseMode::ToStart)");
+    }
+
+    #[test]
+    fn test_parser_device_control() {
+        let input = "A\x1B[sB\x1B[uC\x1B[?25lD\x1B[?25hE";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "ABCDE");
+        let mut save = false;
+        let mut restore = false;
+        let mut hide = false;
+        let mut show = false;
+        for p in &result.points {
+            match p.code {
+                AnsiEscape::Device(DeviceControl::SaveCursor) => save = true,
+                AnsiEscape::Device(DeviceControl::RestoreCursor) => restore = true,
+                AnsiEscape::Device(DeviceControl::HideCursor) => hide = true,
+                AnsiEscape::Device(DeviceControl::ShowCursor) => show = true,
+                _ => {}
+            }
+        }
+        assert!(save, "Did not find DeviceControl::SaveCursor");
+        assert!(restore, "Did not find DeviceControl::RestoreCursor");
         assert!(hide, "Did not find DeviceControl::HideCursor");
         assert!(show, "Did not find DeviceControl::ShowCursor");
     }
 
+    #[test]
+    fn test_parser_cursor_blink() {
+        let result = parse_ansi_annotated("A\x1B[?12hB\x1B[?12lC");
+        assert_eq!(result.text, "ABC");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::SetCursorBlink(true)),
+                AnsiEscape::Device(DeviceControl::SetCursorBlink(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_cursor_blink_combined_with_visibility() {
+        let result = parse_ansi_annotated("\x1B[?12;25hA\x1B[?12;25l");
+        assert_eq!(result.text, "A");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::SetCursorBlink(true)),
+                AnsiEscape::Device(DeviceControl::ShowCursor),
+                AnsiEscape::Device(DeviceControl::SetCursorBlink(false)),
+                AnsiEscape::Device(DeviceControl::HideCursor),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_cursor_position_report() {
+        let result = parse_ansi_annotated("\x1B[12;34R");
+        assert_eq!(result.text, "");
+        assert_eq!(
+            result.points,
+            vec![AnsiPoint {
+                pos: 0,
+                code: AnsiEscape::CursorPositionReport { row: 12, col: 34 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_default_foreground_closes_color_span() {
+        let result = parse_ansi_annotated("\x1B[1;31mab\x1B[39mcd\x1B[44me\x1B[49mf");
+        assert_eq!(result.text, "abcdef");
+        assert_eq!(
+            result.spans,
+            vec![
+                AnsiSpan {
+                    start: 0,
+                    end: 2,
+                    codes: vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)],
+                },
+                AnsiSpan {
+                    start: 2,
+                    end: 4,
+                    codes: vec![SgrAttribute::Bold],
+                },
+                AnsiSpan {
+                    start: 4,
+                    end: 5,
+                    codes: vec![SgrAttribute::Bold, SgrAttribute::Background(Color::Blue)],
+                },
+                AnsiSpan {
+                    start: 5,
+                    end: 6,
+                    codes: vec![SgrAttribute::Bold],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_default_underline_color_closes_span() {
+        let result = parse_ansi_annotated("a\x1B[58;5;42mbc\x1B[59md");
+        assert_eq!(result.text, "abcd");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 1,
+                end: 3,
+                codes: vec![SgrAttribute::UnderlineColor(Color::AnsiValue(42))],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_default_foreground_only_span() {
+        let result = parse_ansi_annotated("\x1B[31mred\x1B[39m plain");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 3,
+                codes: vec![SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_repeat_extends_active_span() {
+        let result = parse_ansi_annotated("\x1B[31mX\x1B[2b\x1B[0mY");
+        assert_eq!(result.text, "XXXY");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 3,
+                codes: vec![SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+        assert!(result.points.is_empty());
+    }
+
+    #[test]
+    fn test_parser_repeat_offsets() {
+        // Multi-byte characters advance both byte and char offsets
+        let input = "\u{65E5}\x1B[b\x1B[1mZ";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "\u{65E5}\u{65E5}Z");
+        assert_eq!((result.spans[0].start, result.spans[0].end), (6, 7));
+        let result = AnsiParser::new(input)
+            .with_char_offsets(true)
+            .parse_annotated();
+        assert_eq!((result.spans[0].start, result.spans[0].end), (2, 3));
+        // Nothing to repeat at the start of the text
+        assert_eq!(parse_ansi_annotated("\x1B[3bA").text, "A");
+    }
+
+    #[test]
+    fn test_parser_repeat_zero_count_is_one() {
+        let events: Vec<_> = AnsiParser::new("a\x1B[0b").events().collect();
+        assert_eq!(
+            events,
+            vec![Event::Text("a"), Event::Escape(AnsiEscape::Repeat(1))]
+        );
+        assert_eq!(parse_ansi_annotated("a\x1B[0bb").text, "aab");
+        assert_eq!(visible_len("a\x1B[0bb"), 3);
+    }
+
+    #[test]
+    fn test_parser_focus_events() {
+        let result = parse_ansi_annotated("\x1B[?1004ha\x1B[Ib\x1B[O\x1B[Ic\x1B[2I");
+        assert_eq!(result.text, "abc");
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::PrivateMode {
+                mode: 1004,
+                enabled: true,
+            }
+        );
+        assert_eq!(result.focus_events(), vec![true, false, true]);
+        // With a count, "I" is still a forward tab
+        assert_eq!(
+            result.points.last().unwrap().code,
+            AnsiEscape::Tab(TabOp::Forward(2))
+        );
+    }
+
+    #[test]
+    fn test_parser_window_size_report() {
+        let result = parse_ansi_annotated("junk\x1B[6;10;20t\x1B[8;24;80tmore");
+        assert_eq!(result.text, "junkmore");
+        assert_eq!(
+            result.points[1].code,
+            AnsiEscape::WindowReport {
+                kind: 8,
+                values: vec![24, 80],
+            }
+        );
+        assert_eq!(result.window_size(), Some((24, 80)));
+        assert_eq!(parse_ansi_annotated("\x1B[6;10;20t").window_size(), None);
+    }
+
+    #[test]
+    fn test_parser_shell_integration_marks() {
+        let input = "\x1B]133;A\x07$ \x1B]133;B\x07ls\x1B]133;C\x1B\\out\x1B]133;D;2\x07";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "$ lsout");
+        let marks: Vec<_> = result
+            .points
+            .iter()
+            .map(|p| (p.pos, p.code.clone()))
+            .collect();
+        assert_eq!(
+            marks,
+            vec![
+                (0, AnsiEscape::ShellIntegration(PromptMark::PromptStart)),
+                (2, AnsiEscape::ShellIntegration(PromptMark::CommandStart)),
+                (4, AnsiEscape::ShellIntegration(PromptMark::CommandExecuted)),
+                (
+                    7,
+                    AnsiEscape::ShellIntegration(PromptMark::CommandFinished(Some(2)))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_shell_integration_exit_code() {
+        let finished = |input: &str| parse_ansi_annotated(input).points[0].code.clone();
+        assert_eq!(
+            finished("\x1B]133;D\x07"),
+            AnsiEscape::ShellIntegration(PromptMark::CommandFinished(None))
+        );
+        assert_eq!(
+            finished("\x1B]133;D;0\x1B\\"),
+            AnsiEscape::ShellIntegration(PromptMark::CommandFinished(Some(0)))
+        );
+        assert_eq!(
+            finished("\x1B]133;D;-1;aid=7\x07"),
+            AnsiEscape::ShellIntegration(PromptMark::CommandFinished(Some(-1)))
+        );
+    }
+
+    #[test]
+    fn test_parser_shell_integration_round_trip() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        for mark in [
+            PromptMark::PromptStart,
+            PromptMark::CommandStart,
+            PromptMark::CommandExecuted,
+            PromptMark::CommandFinished(None),
+            PromptMark::CommandFinished(Some(130)),
+        ] {
+            let code = creator.escape_code(AnsiEscape::ShellIntegration(mark));
+            let result = parse_ansi_annotated(&code);
+            assert_eq!(result.text, "");
+            assert_eq!(result.points[0].code, AnsiEscape::ShellIntegration(mark));
+        }
+    }
+
+    #[test]
+    fn test_parser_other_control_strings_dropped() {
+        let result = parse_ansi_annotated("\x1B]133;Z\x07a\x1B]0;title\x1B\\b\x1BPq#0\x1B\\c");
+        assert!(result.points.is_empty());
+        assert_eq!(result.text, "abc");
+        // Unterminated strings are left as text
+        assert_eq!(parse_ansi_annotated("\x1B]0;title").text, "\x1B]0;title");
+        assert_eq!(parse_ansi_annotated("\x1B_apc").text, "\x1B_apc");
+    }
+
+    #[test]
+    fn test_parser_nf_and_keypad_escapes_dropped() {
+        let result = parse_ansi_annotated("\x1B(Ba\x1B)0b\x1B#8c\x1B=d\x1B>");
+        assert_eq!(result.text, "abcd");
+        assert!(result.points.is_empty());
+    }
+
+    #[test]
+    fn test_parser_csi_cancelled() {
+        // CAN and SUB abort the sequence and are kept, ESC starts a new sequence
+        let result = parse_ansi_annotated("a\x1B[12\x18b\x1B[3\x1B[1mc");
+        assert_eq!(result.text, "a\x18bc");
+        assert_eq!(result.spans[0].codes, vec![SgrAttribute::Bold]);
+        let err = AnsiParser::new("\x1B[1\x1A")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(err.kind, AnsiParseErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_parser_line_breaks_multiline_colored() {
+        let input = "\x1B[31mred\x1B[0m\n\x1B[1;32mbold green\nplain\x1B[0m\n";
+        let result = AnsiParser::new(input)
+            .with_line_breaks(true)
+            .parse_annotated();
+        assert_eq!(result.text, "red\nbold green\nplain\n");
+        assert_eq!(result.line_breaks, vec![3, 14, 20]);
+        for &pos in &result.line_breaks {
+            assert_eq!(result.text.as_bytes()[pos], b'\n');
+        }
+        // Spans and points are unaffected
+        assert_eq!(result.spans, parse_ansi_annotated(input).spans);
+    }
+
+    #[test]
+    fn test_parser_line_breaks_off_by_default() {
+        let result = parse_ansi_annotated("a\nb\n");
+        assert!(result.line_breaks.is_empty());
+    }
+
+    #[test]
+    fn test_parser_line_breaks_char_offsets() {
+        let result = AnsiParser::new("\u{65E5}\u{672C}\n\x1B[1mx\n")
+            .with_char_offsets(true)
+            .with_line_breaks(true)
+            .parse_annotated();
+        assert_eq!(result.line_breaks, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_paste_regions_single_block() {
+        let input = "\x1B[?2004h$ \x1B[200~echo \x1B[1mhi\x1B[0m\x1B[201~\r\n";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, "$ echo hi\r\n");
+        let regions = result.paste_regions();
+        assert_eq!(regions, vec![2..9]);
+        assert_eq!(&result.text[regions[0].clone()], "echo hi");
+    }
+
+    #[test]
+    fn test_parser_paste_markers_and_function_keys() {
+        let result = parse_ansi_annotated("\x1B[200~pasted\x1B[201~\x1B[3~\x1B[15~");
+        assert_eq!(result.text, "pasted");
+        let points: Vec<_> = result.points.into_iter().map(|p| (p.pos, p.code)).collect();
+        assert_eq!(
+            points,
+            vec![
+                (0, AnsiEscape::PasteStart),
+                (6, AnsiEscape::PasteEnd),
+                (6, AnsiEscape::FunctionKey(3)),
+                (6, AnsiEscape::FunctionKey(15)),
+            ]
+        );
+        let creator = creator();
+        assert_eq!(creator.escape_code(AnsiEscape::FunctionKey(5)), "\x1B[5~");
+    }
+
+    #[test]
+    fn test_paste_regions_ignores_unterminated() {
+        let result = parse_ansi_annotated("a\x1B[200~bc\x1B[201~d\x1B[200~e");
+        assert_eq!(result.paste_regions(), vec![1..3]);
+    }
+
+    #[test]
+    fn test_parser_scroll_down_vs_highlight_mouse_tracking() {
+        let result = parse_ansi_annotated("\x1B[T\x1B[3T\x1B[1;2;3;4;5T\x1B[2S");
+        let codes: Vec<_> = result.points.into_iter().map(|p| p.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Scroll(Scroll::Down(1)),
+                AnsiEscape::Scroll(Scroll::Down(3)),
+                AnsiEscape::Device(DeviceControl::InitiateHighlightMouseTracking {
+                    func: 1,
+                    start_col: 2,
+                    start_row: 3,
+                    first_row: 4,
+                    last_row: 5,
+                }),
+                AnsiEscape::Scroll(Scroll::Up(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_scroll_rejects_other_param_counts() {
+        let result = parse_ansi_annotated("a\x1B[1;2Tb\x1B[1;2;3;4;5;6Tc");
+        assert_eq!(result.text, "abc");
+        assert!(result.points.is_empty());
+    }
+
+    #[test]
+    fn test_parser_scroll_round_trip() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        for code in [
+            AnsiEscape::Scroll(Scroll::Up(4)),
+            AnsiEscape::Scroll(Scroll::Down(2)),
+            AnsiEscape::Device(DeviceControl::InitiateHighlightMouseTracking {
+                func: 1,
+                start_col: 10,
+                start_row: 5,
+                first_row: 1,
+                last_row: 24,
+            }),
+        ] {
+            let result = parse_ansi_annotated(&creator.escape_code(code.clone()));
+            assert_eq!(result.points[0].code, code);
+        }
+    }
+
+    #[test]
+    fn test_parser_dec_save_restore_cursor() {
+        let result = parse_ansi_annotated("\x1B7x\x1B8");
+        assert_eq!(result.text, "x");
+        assert_eq!(
+            result.points,
+            vec![
+                AnsiPoint {
+                    pos: 0,
+                    code: AnsiEscape::Device(DeviceControl::SaveCursor),
+                },
+                AnsiPoint {
+                    pos: 1,
+                    code: AnsiEscape::Device(DeviceControl::RestoreCursor),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_full_reset_and_reverse_index() {
+        let result = parse_ansi_annotated("\x1Bca\x1BMb");
+        assert_eq!(result.text, "ab");
+        let codes: Vec<_> = result.points.into_iter().map(|p| p.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::FullReset),
+                AnsiEscape::Device(DeviceControl::ReverseIndex),
+            ]
+        );
+        let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+        for device in [DeviceControl::FullReset, DeviceControl::ReverseIndex] {
+            let result = parse_ansi_annotated(&creator.device_code(device));
+            assert_eq!(result.points[0].code, AnsiEscape::Device(device));
+        }
+    }
+
+    #[test]
+    fn test_parser_private_modes() {
+        let result = parse_ansi_annotated("\x1B[?1049hA\x1B[?2004l");
+        assert_eq!(result.text, "A");
+        assert_eq!(
+            result.points,
+            vec![
+                AnsiPoint {
+                    pos: 0,
+                    code: AnsiEscape::Device(DeviceControl::EnterAltScreen),
+                },
+                AnsiPoint {
+                    pos: 1,
+                    code: AnsiEscape::PrivateMode {
+                        mode: 2004,
+                        enabled: false,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_private_modes_with_extra_params() {
+        // Cursor visibility combined with other modes, plus junk from a buggy emitter
+        let result = parse_ansi_annotated("\x1B[?25;2004lA\x1B[?25;;2004;hB");
+        assert_eq!(result.text, "AB");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::HideCursor),
+                AnsiEscape::PrivateMode {
+                    mode: 2004,
+                    enabled: false,
+                },
+                AnsiEscape::Device(DeviceControl::ShowCursor),
+                AnsiEscape::PrivateMode {
+                    mode: 2004,
+                    enabled: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_alt_screen_round_trip() {
+        let creator = creator();
+        for device in [
+            DeviceControl::EnterAltScreen,
+            DeviceControl::ExitAltScreen,
+            DeviceControl::AltScreen {
+                mode: AltScreenMode::Plain,
+                enabled: true,
+            },
+            DeviceControl::AltScreen {
+                mode: AltScreenMode::ClearOnExit,
+                enabled: false,
+            },
+        ] {
+            let result = parse_ansi_annotated(&creator.device_code(device));
+            assert_eq!(
+                result.points,
+                vec![AnsiPoint {
+                    pos: 0,
+                    code: AnsiEscape::Device(device),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parser_alt_screen_modes() {
+        let result = parse_ansi_annotated("\x1B[?47h\x1B[?1047;1049lx");
+        let modes: Vec<_> = result
+            .points
+            .iter()
+            .map(|point| match &point.code {
+                AnsiEscape::Device(device) => device.alt_screen_mode(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            modes,
+            [
+                Some(AltScreenMode::Plain),
+                Some(AltScreenMode::ClearOnExit),
+                Some(AltScreenMode::SaveCursor),
+            ]
+        );
+        assert_eq!(
+            result.points[1].code,
+            AnsiEscape::Device(DeviceControl::AltScreen {
+                mode: AltScreenMode::ClearOnExit,
+                enabled: false,
+            })
+        );
+        assert!(modes[1].unwrap().clears_on_exit());
+        assert_eq!(result.text, "x");
+    }
+
+    #[test]
+    fn test_parser_kitty_keyboard_vs_restore_cursor() {
+        let result = parse_ansi_annotated("\x1B[uA\x1B[>1uB\x1B[<u");
+        assert_eq!(result.text, "AB");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::RestoreCursor),
+                AnsiEscape::Device(DeviceControl::SetKittyKeyboard(
+                    KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+                )),
+                AnsiEscape::Device(DeviceControl::PopKittyKeyboard(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_kitty_keyboard_flags() {
+        let result = parse_ansi_annotated("\x1B[>5u");
+        let AnsiEscape::Device(DeviceControl::SetKittyKeyboard(flags)) = result.points[0].code
+        else {
+            panic!("expected kitty keyboard flags: {:?}", result.points);
+        };
+        assert!(flags.contains(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES));
+        assert!(flags.contains(KittyKeyboardFlags::REPORT_ALTERNATE_KEYS));
+        assert!(!flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES));
+        assert_eq!(flags.bits(), 5);
+    }
+
+    #[test]
+    fn test_parser_mouse_press_and_release() {
+        let result = parse_ansi_annotated("\x1B[<0;15;8Mx\x1B[<0;16;9m");
+        assert_eq!(result.text, "x");
+        assert!(result.spans.is_empty());
+        assert_eq!(
+            result.points,
+            vec![
+                AnsiPoint {
+                    pos: 0,
+                    code: AnsiEscape::Mouse {
+                        button: 0,
+                        col: 15,
+                        row: 8,
+                        pressed: true,
+                    },
+                },
+                AnsiPoint {
+                    pos: 1,
+                    code: AnsiEscape::Mouse {
+                        button: 0,
+                        col: 16,
+                        row: 9,
+                        pressed: false,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_preserves_nul() {
+        let result = parse_ansi_annotated("a\0\x1B[31mb");
+        assert_eq!(result.text, "a\0b");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 2,
+                end: 3,
+                codes: vec![SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_events_mixed_input() {
+        let events: Vec<_> = AnsiParser::new("ab\x1B[1;31mc\x1B[999y\x1Bd\x1B[2J")
+            .events()
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Text("ab"),
+                Event::Escape(AnsiEscape::Sgr(SgrAttribute::Bold)),
+                Event::Escape(AnsiEscape::Sgr(SgrAttribute::Foreground(Color::Red))),
+                Event::Text("c"),
+                Event::Text("\x1Bd"),
+                Event::Escape(AnsiEscape::Erase(Erase::Display(EraseMode::All))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_runs_match_char_by_char_parse() {
+        // Reference: the cleaned text and offsets built one char at a time
+        fn char_by_char(input: &str, options: ParseOptions) -> AnsiParseResult {
+            let mut parser = AnsiParser::new(input).with_options(options);
+            let mut cleaned = String::new();
+            let mut line_breaks = Vec::new();
+            let mut points = Vec::new();
+            while parser.pos < input.len() {
+                if let Some((escapes, consumed)) = parser.parse_next_escapes() {
+                    for code in escapes {
+                        if matches!(code, AnsiEscape::Sgr(_)) {
+                            continue;
+                        }
+                        points.push(AnsiPoint {
+                            pos: parser.offset(),
+                            code,
+                        });
+                    }
+                    parser.pos += consumed;
+                } else {
+                    let ch = input[parser.pos..].chars().next().unwrap();
+                    parser.push_char(&mut cleaned, &mut line_breaks, ch);
+                    parser.pos += ch.len_utf8();
+                }
+            }
+            AnsiParseResult {
+                text: cleaned,
+                spans: vec![],
+                points,
+                line_breaks,
+            }
+        }
+        let inputs = [
+            "",
+            "plain ascii only",
+            "\u{65E5}\u{672C}\u{00A3}\u{00E9} \x1B[2Cx",
+            "lone \x1B esc \x1Bx and \u{00C2}\u{009B}1A c1",
+            "a\nb\x1B[1m\nc\x1B[0m\r\n\u{00A3}\n",
+            "\x1B[31m\x1B]133;A\x07$ \x1B[200~ls\x1B[201~\x1B",
+        ];
+        for input in inputs {
+            for (char_offsets, line_breaks) in
+                [(false, false), (true, false), (false, true), (true, true)]
+            {
+                let options = ParseOptions {
+                    char_offsets,
+                    line_breaks,
+                    ..ParseOptions::default()
+                };
+                let expected = char_by_char(input, options);
+                let result = parse_ansi_annotated_with(input, &options);
+                assert_eq!(result.text, expected.text, "{:?}", input);
+                assert_eq!(result.points, expected.points, "{:?}", input);
+                assert_eq!(result.line_breaks, expected.line_breaks, "{:?}", input);
+                let events: String = AnsiParser::new(input)
+                    .with_options(options)
+                    .events()
+                    .filter_map(|e| match e {
+                        Event::Text(text) => Some(text),
+                        Event::Escape(_) => None,
+                    })
+                    .collect();
+                assert_eq!(events, expected.text, "{:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_match_parse_text() {
+        let input = "x\x1B[4m\u{65E5}\x1B[0my\x1B[?25l";
+        let text: String = AnsiParser::new(input)
+            .events()
+            .filter_map(|e| match e {
+                Event::Text(t) => Some(t),
+                Event::Escape(_) => None,
+            })
+            .collect();
+        assert_eq!(text, parse_ansi_annotated(input).text);
+    }
+
+    #[test]
+    fn test_parser_empty_sgr_is_reset() {
+        let result = parse_ansi_annotated("a\x1B[1mb\x1B[mc");
+        assert_eq!(result.text, "abc");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 1,
+                end: 2,
+                codes: vec![SgrAttribute::Bold],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_tab_round_trip() {
+        let creator = creator();
+        for (input, expected) in [
+            ("\x1B[2I", TabOp::Forward(2)),
+            ("\x1B[Z", TabOp::Backward(1)),
+            ("\x1B[3g", TabOp::Clear(TabClear::All)),
+            ("\x1B[g", TabOp::Clear(TabClear::Current)),
+            ("\x1BH", TabOp::Set),
+        ] {
+            let result = parse_ansi_annotated(input);
+            assert_eq!(result.text, "");
+            assert_eq!(result.points[0].code, AnsiEscape::Tab(expected));
+            let reparsed = parse_ansi_annotated(&creator.tab_code(expected));
+            assert_eq!(reparsed.points, result.points);
+        }
+        assert_eq!(creator.tab_code(TabOp::Forward(2)), "\x1B[2I");
+        assert_eq!(creator.tab_code(TabOp::Clear(TabClear::All)), "\x1B[3g");
+    }
+
+    #[test]
+    fn test_parser_soft_reset_round_trip() {
+        let result = parse_ansi_annotated("\x1B[!p");
+        assert_eq!(
+            result.points,
+            vec![AnsiPoint {
+                pos: 0,
+                code: AnsiEscape::Device(DeviceControl::SoftReset),
+            }]
+        );
+        assert_eq!(creator().device_code(DeviceControl::SoftReset), "\x1B[!p");
+    }
+
+    #[test]
+    fn test_parser_intermediate_bytes_do_not_break_neighbours() {
+        // DECSCUSR ("ESC [ 2 SP q") and DECSTR ("ESC [ ! p") must not swallow what follows
+        let result = parse_ansi_annotated("a\x1B[2 qb\x1B[!pc\x1B[31md\x1B[5p");
+        assert_eq!(result.text, "abcd");
+        let codes: Vec<_> = result.points.iter().map(|p| p.code.clone()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                AnsiEscape::Device(DeviceControl::SetCursorStyle(CursorStyle::SteadyBlock)),
+                AnsiEscape::Device(DeviceControl::SoftReset),
+            ]
+        );
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Foreground(Color::Red)]
+        );
+    }
+
+    #[test]
+    fn test_parser_linux_palette() {
+        // ESC ] P plus exactly seven hex digits are consumed; the trailing "d" is text
+        let result = parse_ansi_annotated("a\x1B]P1aabbccd");
+        assert_eq!(result.text, "ad");
+        assert_eq!(
+            result.points,
+            vec![AnsiPoint {
+                pos: 1,
+                code: AnsiEscape::Device(DeviceControl::LinuxPalette {
+                    index: 1,
+                    r: 0xaa,
+                    g: 0xbb,
+                    b: 0xcc,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_linux_palette_reset() {
+        let result = parse_ansi_annotated("a\x1B]Rb");
+        assert_eq!(result.text, "ab");
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::Device(DeviceControl::LinuxPaletteReset)
+        );
+    }
+
+    #[test]
+    fn test_parser_linux_palette_invalid_is_not_consumed() {
+        let result = parse_ansi_annotated("\x1B]Pzzzzzzz");
+        assert!(result.points.is_empty());
+        assert_eq!(result.text, "\x1B]Pzzzzzzz");
+    }
+
+    #[test]
+    fn test_strict_truncated_sequence() {
+        let err = AnsiParser::new("abc\x1B[1mdef\x1B[")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            AnsiParseError {
+                offset: 10,
+                kind: AnsiParseErrorKind::Truncated,
+            }
+        );
+        // Lenient parsing is unchanged
+        assert_eq!(parse_ansi_annotated("abc\x1B[1mdef\x1B[").text, "abcdef");
+    }
+
+    #[test]
+    fn test_strict_unknown_final_byte() {
+        let err = AnsiParser::new("a\x1B[5zb")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(err.offset, 1);
+        assert_eq!(err.kind, AnsiParseErrorKind::UnknownFinalByte(b'z'));
+        assert_eq!(parse_ansi_annotated("a\x1B[5zb").text, "ab");
+    }
+
+    #[test]
+    fn test_strict_bad_color_param() {
+        let err = AnsiParser::new("\x1B[38;2;255;0m")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(err.kind, AnsiParseErrorKind::BadColorParam);
+        let err = AnsiParser::new("\x1B[48;5;300m")
+            .parse_annotated_strict()
+            .unwrap_err();
+        assert_eq!(err.kind, AnsiParseErrorKind::BadColorParam);
+    }
+
+    #[test]
+    fn test_strict_valid_input_matches_lenient() {
+        let input = "a\x1B[1;38;5;9mb\x1B[2J\x1B[0mc\x1B[?25l\x1B[<0;1;2M";
+        let strict = AnsiParser::new(input).parse_annotated_strict().unwrap();
+        assert_eq!(strict, parse_ansi_annotated(input));
+    }
+
+    #[test]
+    fn test_strip_lone_esc_enabled() {
+        let result = AnsiParser::new("\x1Bx")
+            .with_strip_lone_esc(true)
+            .parse_annotated();
+        assert_eq!(result.text, "x");
+        let result = AnsiParser::new("a\x1B\x1B[1mb\x1B")
+            .with_strip_lone_esc(true)
+            .parse_annotated();
+        assert_eq!(result.text, "ab");
+        assert_eq!(result.spans[0].codes, vec![SgrAttribute::Bold]);
+    }
+
+    #[test]
+    fn test_parser_c1_csi() {
+        let result = parse_ansi_annotated("a\u{9B}31mb\u{9B}0mc\u{9B}2J");
+        assert_eq!(result.text, "abc");
+        assert_eq!(result.spans.len(), 1);
+        assert_eq!(result.spans[0].start, 1);
+        assert_eq!(result.spans[0].end, 2);
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Foreground(Color::Red)]
+        );
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::Erase(Erase::Display(EraseMode::All))
+        );
+    }
+
+    #[test]
+    fn test_parser_c1_csi_not_matched_inside_other_chars() {
+        // U+06DB is encoded as 0xDB 0x9B and U+029B as 0xCA 0x9B: the 0x9B byte must not
+        // be taken for a CSI introducer.
+        let input = "\u{6DB}31m\u{29B}0m";
+        let result = parse_ansi_annotated(input);
+        assert_eq!(result.text, input);
+        assert!(result.spans.is_empty());
+    }
+
+    #[test]
+    fn test_events_c1_csi() {
+        let events: Vec<Event> = AnsiParser::new("a\u{9B}1mb").events().collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Text("a"),
+                Event::Escape(AnsiEscape::Sgr(SgrAttribute::Bold)),
+                Event::Text("b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_options_combined() {
+        let options = ParseOptions {
+            char_offsets: true,
+            param_separator: ',',
+            preserve_unknown: true,
+            ..ParseOptions::default()
+        };
+        let input = "\u{65E5}\x1B[1,31mA\x1B[999y\x1B[2,3H";
+        let result = parse_ansi_annotated_with(input, &options);
+        assert_eq!(result.text, "\u{65E5}A");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 1,
+                end: 2,
+                codes: vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+        assert_eq!(
+            result.points,
+            vec![
+                AnsiPoint {
+                    pos: 2,
+                    code: AnsiEscape::Unknown {
+                        params: "999".to_string(),
+                        final_byte: b'y',
+                    },
+                },
+                AnsiPoint {
+                    pos: 2,
+                    code: AnsiEscape::Cursor(CursorMove::Position { row: 2, col: 3 }),
+                },
+            ]
+        );
+        // The builder methods set the same options
+        let built = AnsiParser::new(input)
+            .with_char_offsets(true)
+            .with_param_separator(',')
+            .with_preserve_unknown(true)
+            .parse_annotated();
+        assert_eq!(built, result);
+    }
+
+    #[test]
+    fn test_parse_options_default_matches_plain_parse() {
+        let input = "a\x1B[1mb\x1B[999yc\x1Bd";
+        assert_eq!(
+            parse_ansi_annotated_with(input, &ParseOptions::default()),
+            parse_ansi_annotated(input)
+        );
+    }
+
+    #[test]
+    fn test_param_separator_comma() {
+        let result = AnsiParser::new("\x1B[1,31mA\x1B[3,7H")
+            .with_param_separator(',')
+            .parse_annotated();
+        assert_eq!(result.text, "A");
+        assert_eq!(
+            result.spans[0].codes,
+            vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)]
+        );
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::Cursor(CursorMove::Position { row: 3, col: 7 })
+        );
+    }
+
+    #[test]
+    fn test_param_separator_default_is_semicolon() {
+        let result = parse_ansi_annotated("\x1B[1,31mA");
+        assert!(
+            !result
+                .spans
+                .iter()
+                .any(|s| s.codes.contains(&SgrAttribute::Foreground(Color::Red)))
+        );
+    }
+
+    #[test]
+    fn test_strip_lone_esc_disabled() {
+        let result = AnsiParser::new("\x1Bx").parse_annotated();
+        assert_eq!(result.text, "\x1Bx");
+        let result = AnsiParser::new("\x1Bx")
+            .with_strip_lone_esc(false)
+            .parse_annotated();
+        assert_eq!(result.text, "\x1Bx");
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_truecolor: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        let input = "a\x1B[1mb\x1B[31mc\x1B[2Jd\x1B[0me\x1B[38;2;1;2;3;48;5;9mf\x1B[?25l";
+        let result = parse_ansi_annotated(input);
+        let rendered = result.render(&creator);
+        let reparsed = parse_ansi_annotated(&rendered);
+        assert_eq!(reparsed.text, result.text);
+        assert_eq!(reparsed.spans, result.spans);
+        assert_eq!(reparsed.points, result.points);
+    }
+
+    #[test]
+    fn test_erase_closes_spans() {
+        let parse = |input| {
+            AnsiParser::new(input)
+                .with_erase_closes_spans(true)
+                .parse_annotated()
+                .spans
+                .iter()
+                .map(|s| (s.start, s.end))
+                .collect::<Vec<_>>()
+        };
+        // Erase to start: the erased part is dropped from the open span
+        assert_eq!(parse("\x1B[31mABC\x1B[1K"), vec![]);
+        assert_eq!(parse("\x1B[31mABC\x1B[1KDEF"), vec![(3, 6)]);
+        assert_eq!(parse("\x1B[31mABC\x1B[2KDEF"), vec![(3, 6)]);
+        // Erase to end: the open span is split at the erase
+        assert_eq!(parse("\x1B[31mAB\x1B[KCD"), vec![(0, 2), (2, 4)]);
+        // Off by default
+        let result = parse_ansi_annotated("\x1B[31mABC\x1B[1KDEF");
+        assert_eq!((result.spans[0].start, result.spans[0].end), (0, 6));
+    }
+
+    #[test]
+    fn test_final_cursor_state() {
+        let result = parse_ansi_annotated("\x1B[?25la\x1B[5 qb\x1B[?25hc");
+        assert_eq!(result.final_cursor_visible(), Some(true));
+        assert_eq!(result.final_cursor_style(), Some(CursorStyle::BlinkingBar));
+        let result = parse_ansi_annotated("\x1B[?25ha\x1B[?25l");
+        assert_eq!(result.final_cursor_visible(), Some(false));
+        let result = parse_ansi_annotated("\x1B[2Jplain");
+        assert_eq!(result.final_cursor_visible(), None);
+        assert_eq!(result.final_cursor_style(), None);
+        // Unknown shapes are not modelled
+        assert_eq!(parse_ansi_annotated("\x1B[9 q").points, vec![]);
+    }
+
+    #[test]
+    fn test_render_keeps_alt_screen_points() {
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        });
+        let input = "shell\x1B[?1049h\x1B[1mapp\x1B[0m\x1B[?1049lback";
+        let result = parse_ansi_annotated(input);
+        let rendered = result.render(&creator);
+        assert_eq!(rendered.find("\x1B[?1049h"), Some("shell".len()));
+        assert!(rendered.ends_with("\x1B[0m\x1B[?1049lback"));
+        let reparsed = parse_ansi_annotated(&rendered);
+        assert_eq!(reparsed.points, result.points);
+        assert_eq!(
+            reparsed.points[0],
+            AnsiPoint {
+                pos: 5,
+                code: AnsiEscape::Device(DeviceControl::EnterAltScreen),
+            }
+        );
+    }
+
+    #[test]
+    fn test_collapse_adjacent_zero_length_spans() {
+        let mut result = AnsiParseResult {
+            text: "abc".to_string(),
+            spans: vec![
+                AnsiSpan {
+                    start: 0,
+                    end: 0,
+                    codes: vec![SgrAttribute::Bold],
+                },
+                AnsiSpan {
+                    start: 0,
+                    end: 0,
+                    codes: vec![SgrAttribute::Foreground(Color::Green)],
+                },
+                AnsiSpan {
+                    start: 0,
+                    end: 3,
+                    codes: vec![SgrAttribute::Foreground(Color::Red)],
+                },
+            ],
+            points: vec![],
+            line_breaks: vec![],
+        };
+        result.collapse_adjacent_sgr();
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 3,
+                codes: vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_collapse_adjacent_sgr_keeps_parser_output() {
+        let mut result = parse_ansi_annotated("\x1B[1m\x1B[31mx");
+        let spans = result.spans.clone();
+        assert_eq!(
+            spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 1,
+                codes: vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+        result.collapse_adjacent_sgr();
+        assert_eq!(result.spans, spans);
+    }
+
+    #[test]
+    fn test_collapse_adjacent_identical_spans() {
+        let mut result = parse_ansi_annotated("\x1B[1mab\x1B[0m\x1B[1mcd\x1B[31me");
+        assert_eq!(result.spans.len(), 3);
+        result.collapse_adjacent_sgr();
+        assert_eq!(
+            result.spans,
+            vec![
+                AnsiSpan {
+                    start: 0,
+                    end: 4,
+                    codes: vec![SgrAttribute::Bold],
+                },
+                AnsiSpan {
+                    start: 4,
+                    end: 5,
+                    codes: vec![SgrAttribute::Bold, SgrAttribute::Foreground(Color::Red)],
+                },
+            ]
+        );
+        assert_eq!(result.text, "abcde");
+    }
+
+    #[test]
+    fn test_styled_words_split_at_style_change() {
+        let result = parse_ansi_annotated("one ha\x1B[31mlf\x1B[0m  two\n\x1B[1mbold\x1B[0m");
+        let red = vec![SgrAttribute::Foreground(Color::Red)];
+        assert_eq!(
+            result.styled_words(),
+            vec![
+                ("one".to_string(), vec![]),
+                ("ha".to_string(), vec![]),
+                ("lf".to_string(), red),
+                ("two".to_string(), vec![]),
+                ("bold".to_string(), vec![SgrAttribute::Bold]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_styled_words_empty_and_whitespace_only() {
+        assert!(parse_ansi_annotated("").styled_words().is_empty());
+        assert!(
+            parse_ansi_annotated("\x1B[1m \t\n")
+                .styled_words()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_coalesce_reapplied_style() {
+        let mut result =
+            parse_ansi_annotated("\x1B[31mab\x1B[0m\x1B[31m\x1B[31mcd\x1B[0m\x1B[32me\x1B[0mf");
+        assert_eq!(result.spans.len(), 3);
+        result.coalesce();
+        assert_eq!(
+            result.spans,
+            vec![
+                AnsiSpan {
+                    start: 0,
+                    end: 4,
+                    codes: vec![SgrAttribute::Foreground(Color::Red)],
+                },
+                AnsiSpan {
+                    start: 4,
+                    end: 5,
+                    codes: vec![SgrAttribute::Foreground(Color::Green)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_keeps_gaps() {
+        let mut result = parse_ansi_annotated("\x1B[31ma\x1B[0m-\x1B[31mb");
+        result.coalesce();
+        assert_eq!(result.spans.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_points_removes_cursor_moves() {
+        let mut result = parse_ansi_annotated("a\x1B[1mb\x1B[2Ac\x1B[2Jd\x1B[3Ge\x1B[0m");
+        let text = result.text.clone();
+        let spans = result.spans.clone();
+        result.filter_points(|p| !matches!(p.code, AnsiEscape::Cursor(_)));
+        assert_eq!(result.points.len(), 1);
+        assert_eq!(
+            result.points[0].code,
+            AnsiEscape::Erase(Erase::Display(EraseMode::All))
+        );
+        assert_eq!(result.text, text);
+        assert_eq!(result.spans, spans);
+    }
+
+    #[test]
+    fn test_map_points_shifts_positions() {
+        let mut result = parse_ansi_annotated("a\x1B[1mb\x1B[2Ac\x1B[2Jd");
+        let spans = result.spans.clone();
+        result.map_points(|p| AnsiPoint {
+            pos: p.pos + 10,
+            ..p
+        });
+        let positions: Vec<usize> = result.points.iter().map(|p| p.pos).collect();
+        assert_eq!(positions, vec![12, 13]);
+        assert_eq!(result.text, "abcd");
+        assert_eq!(result.spans, spans);
+    }
+
+    #[test]
+    fn test_visible_len_matches_cleaned_text() {
+        for input in [
+            "",
+            "plain",
+            "\x1B[31mred\x1B[0m and \x1B[1mbold\x1B[0m",
+            "\u{65E5}\u{672C}\x1B[2J\u{00E9}",
+            "A\x1B[31B\x1B[999yC\x1B[38;2;1;2mD",
+            "lone \x1B esc and truncated \x1B[12",
+            "x\x1B[3b\x1B[5b",
+            "\x1B]133;A\x07prompt\x1B]8;;no\x07",
+        ] {
+            assert_eq!(
+                visible_len(input),
+                parse_ansi_annotated(input).text.chars().count(),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_visible_len_counts_chars_not_columns() {
+        assert_eq!(visible_len("\x1B[1m\u{65E5}\u{672C}\x1B[0m"), 2);
+    }
+
+    #[test]
+    fn test_display_width_mixed() {
+        // "ab" (2) + two CJK characters (4) + "e" with a combining acute accent (1)
+        let result = parse_ansi_annotated("\x1B[31mab\u{65E5}\u{672C}\x1B[0me\u{301}");
+        assert_eq!(result.display_width(), 7);
+    }
+
+    #[test]
+    fn test_display_width_multiline() {
+        let result = parse_ansi_annotated("ab\n\x1B[1m\u{65E5}\u{672C}\u{8A9E}\x1B[0m\r\nc\n");
+        assert_eq!(result.display_width(), 6);
+        assert_eq!(parse_ansi_annotated("").display_width(), 0);
+    }
+
+    #[test]
+    fn test_to_irc_red_bold() {
+        let result = parse_ansi_annotated("a\x1B[1;31mred\x1B[0m\x1B[2Jb");
+        assert_eq!(result.to_irc(), "a\x02\x0305red\x0Fb");
+    }
+
+    #[test]
+    fn test_to_irc_colors_and_attributes() {
+        let result = parse_ansi_annotated("\x1B[4;92;44mx\x1B[0m\x1B[7;48;2;255;255;255my\x1B[0m");
+        assert_eq!(result.to_irc(), "\x1F\x0309,02x\x0F\x16\x0399,00y\x0F");
+    }
+
+    #[test]
+    fn test_to_irc_underline_none() {
+        assert_eq!(parse_ansi_annotated("\x1B[4:0mx").to_irc(), "x");
+        // A hand-built span with `4:0` is not underlined either
+        let result = AnsiParseResult {
+            text: "xy".to_string(),
+            spans: vec![AnsiSpan {
+                start: 0,
+                end: 1,
+                codes: vec![
+                    SgrAttribute::Bold,
+                    SgrAttribute::UnderlineStyle(UnderlineKind::None),
+                ],
+            }],
+            points: vec![],
+            line_breaks: vec![],
+        };
+        assert_eq!(result.to_irc(), "\x02x\x0Fy");
+    }
+
+    #[test]
+    fn test_byte_to_display_column() {
+        let result = parse_ansi_annotated("a\tb\x1B[31m\u{65E5}c\x1B[0m\nxy");
+        assert_eq!(result.text, "a\tb\u{65E5}c\nxy");
+        // "a" -> 1, tab -> 4, "b" -> 5, wide char -> 7
+        assert_eq!(result.byte_to_display_column(3, 4), 5);
+        assert_eq!(result.byte_to_display_column(6, 4), 7);
+        assert_eq!(result.byte_to_display_column(7, 4), 8);
+        assert_eq!(result.byte_to_display_column(7, 8), 12);
+        // Columns restart after a newline
+        assert_eq!(result.byte_to_display_column(9, 4), 1);
+    }
+
+    #[test]
+    fn test_parser_overline_span() {
+        let result = parse_ansi_annotated("A\x1B[53mBC\x1B[55mD\x1B[1;53mE\x1B[55mF");
+        assert_eq!(result.text, "ABCDEF");
+        assert_eq!(
+            result.spans,
+            vec![
+                AnsiSpan {
+                    start: 1,
+                    end: 3,
+                    codes: vec![SgrAttribute::Overline],
+                },
+                AnsiSpan {
+                    start: 4,
+                    end: 5,
+                    codes: vec![SgrAttribute::Bold, SgrAttribute::Overline],
+                },
+                AnsiSpan {
+                    start: 5,
+                    end: 6,
+                    codes: vec![SgrAttribute::Bold],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_not_blinking_ends_span() {
+        let result = parse_ansi_annotated("\x1B[5mtext\x1B[25mmore");
+        assert_eq!(result.text, "textmore");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 4,
+                codes: vec![SgrAttribute::BlinkSlow],
+            }]
+        );
+        // Both blink speeds are cleared, other attributes stay
+        let result = parse_ansi_annotated("\x1B[1;5;6mA\x1B[25mB");
+        assert_eq!(result.spans[1].codes, vec![SgrAttribute::Bold]);
+        assert!(result.points.is_empty());
+    }
+
+    #[test]
+    fn test_parser_framed_encircled_span() {
+        let result = parse_ansi_annotated("A\x1B[51mB\x1B[52mC\x1B[54mD");
+        assert_eq!(result.text, "ABCD");
+        assert_eq!(
+            result.spans,
+            vec![
+                AnsiSpan {
+                    start: 1,
+                    end: 2,
+                    codes: vec![SgrAttribute::Framed],
+                },
+                AnsiSpan {
+                    start: 2,
+                    end: 3,
+                    codes: vec![SgrAttribute::Framed, SgrAttribute::Encircled],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_framed_encircled_round_trip() {
+        let creator = creator();
+        for attr in [
+            SgrAttribute::Framed,
+            SgrAttribute::Encircled,
+            SgrAttribute::NotFramedEncircled,
+        ] {
+            let code = creator.sgr_code(attr);
+            let parsed: Vec<_> = AnsiParser::new(&code).events().collect();
+            assert_eq!(parsed, vec![Event::Escape(AnsiEscape::Sgr(attr))]);
+        }
+    }
+
+    #[test]
+    fn test_parser_overline_round_trip() {
+        let creator = creator();
+        let input = format!(
+            "{}x{}y",
+            creator.sgr_code(SgrAttribute::Overline),
+            creator.sgr_code(SgrAttribute::NotOverline)
+        );
+        let result = parse_ansi_annotated(&input);
+        assert_eq!(result.text, "xy");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 0,
+                end: 1,
+                codes: vec![SgrAttribute::Overline],
+            }]
+        );
+    }
+
     #[test]
     fn test_parser_malformed_sequences() {
         // Malformed or incomplete escape sequences should be ignored/skipped
-        let input = "A\x1B[31B\x1B[999ZC\x1B[38;2;1;2mD";
+        let input = "A\x1B[31B\x1B[999yC\x1B[38;2;1;2mD";
         let result = parse_ansi_annotated(input);
         assert_eq!(result.text, "ACD");
         // Should not panic or produce unknown codes
@@ -554,31 +3986,85 @@ mod tests {
                 AnsiEscape::Sgr(_)
                 | AnsiEscape::Cursor(_)
                 | AnsiEscape::Erase(_)
-                | AnsiEscape::Device(_) => {}
+                | AnsiEscape::Device(_)
+                | AnsiEscape::Tab(_)
+                | AnsiEscape::Scroll(_)
+                | AnsiEscape::CursorPositionReport { .. }
+                | AnsiEscape::PrivateMode { .. }
+                | AnsiEscape::Mouse { .. }
+                | AnsiEscape::WindowReport { .. }
+                | AnsiEscape::Focus { .. }
+                | AnsiEscape::ShellIntegration(_)
+                | AnsiEscape::PasteStart
+                | AnsiEscape::PasteEnd
+                | AnsiEscape::FunctionKey(_)
+                | AnsiEscape::Repeat(_) => {}
+                AnsiEscape::Unknown { .. } => panic!("unknown code reported by default"),
             }
         }
     }
 
+    #[test]
+    fn test_parser_preserve_unknown() {
+        let result = AnsiParser::new("A\x1B[999yB")
+            .with_preserve_unknown(true)
+            .parse_annotated();
+        assert_eq!(result.text, "AB");
+        assert_eq!(
+            result.points,
+            vec![AnsiPoint {
+                pos: 1,
+                code: AnsiEscape::Unknown {
+                    params: "999".to_string(),
+                    final_byte: b'y',
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parser_preserve_unknown_round_trip() {
+        let input = "A\x1B[999yB\x1B[2JC\x1B[?5;7Q";
+        let result = AnsiParser::new(input)
+            .with_preserve_unknown(true)
+            .parse_annotated();
+        let creator = creator();
+        assert_eq!(result.render(&creator), input);
+    }
+
+    #[test]
+    fn test_parser_char_offsets() {
+        let input = "\u{1F600}\u{1F600}\x1B[31mX\x1B[0m\x1B[2J";
+        let result = AnsiParser::new(input)
+            .with_char_offsets(true)
+            .parse_annotated();
+        assert_eq!(result.text, "\u{1F600}\u{1F600}X");
+        assert_eq!(
+            result.spans,
+            vec![AnsiSpan {
+                start: 2,
+                end: 3,
+                codes: vec![SgrAttribute::Foreground(Color::Red)],
+            }]
+        );
+        assert_eq!(result.points[0].pos, 3);
+
+        // Byte offsets remain the default
+        let result = parse_ansi_annotated(input);
+        assert_eq!((result.spans[0].start, result.spans[0].end), (8, 9));
+    }
+
     #[test]
     fn test_parser_multiple_sgr_in_one_sequence() {
-        // Only the first SGR is returned as a point, but all should be parsed
+        // All SGRs in a single sequence are applied to the same span
         let input = "A\x1B[1;31;4mB\x1B[0m";
         let result = parse_ansi_annotated(input);
         assert_eq!(result.text, "AB");
-        let sgr_points: Vec<_> = result
-            .points
-            .iter()
-            .filter_map(|p| {
-                if let AnsiEscape::Sgr(attr) = p.code {
-                    Some(attr)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        assert!(sgr_points.contains(&SgrAttribute::Bold));
-        assert!(sgr_points.contains(&SgrAttribute::Foreground(Color::Red)));
-        assert!(sgr_points.contains(&SgrAttribute::Underline));
-        assert!(sgr_points.contains(&SgrAttribute::Reset));
+        assert_eq!(result.spans.len(), 1);
+        let codes = &result.spans[0].codes;
+        assert!(codes.contains(&SgrAttribute::Bold));
+        assert!(codes.contains(&SgrAttribute::Foreground(Color::Red)));
+        assert!(codes.contains(&SgrAttribute::Underline));
+        assert_eq!((result.spans[0].start, result.spans[0].end), (1, 2));
     }
 }
@@ -0,0 +1,223 @@
+//! ansi_paint.rs
+//!
+//! Extension trait for styling strings inline, e.g. `"error".red().bold()`.
+
+use super::ansi_creator::{AnsiCreator, AnsiEnvironment};
+use super::ansi_types::{Color, SgrAttribute};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Text together with the SGR attributes to display it with.
+///
+/// Created by the [`Paint`] methods. Formatting with `Display` emits the codes lazily,
+/// as a terminal with full color support would expect them; use [`Painted::render`] to
+/// produce output suited to a specific environment instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Painted<'a> {
+    text: &'a str,
+    attrs: Vec<SgrAttribute>,
+}
+
+impl Painted<'_> {
+    /// The attributes applied so far, in the order they were added.
+    pub fn attrs(&self) -> &[SgrAttribute] {
+        &self.attrs
+    }
+
+    /// Format the text with `creator`, so colors follow its environment's capabilities.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AnsiCreator, AnsiEnvironment, Paint};
+    /// let plain = AnsiCreator::with_environment(AnsiEnvironment::default());
+    /// assert_eq!("hi".red().render(&plain), "hi");
+    /// ```
+    pub fn render(&self, creator: &AnsiCreator) -> String {
+        creator.format_text(self.text, &self.attrs)
+    }
+}
+
+impl fmt::Display for Painted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.attrs.is_empty() {
+            return f.write_str(self.text);
+        }
+        let creator = AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            supports_truecolor: true,
+            supports_8bit_color: true,
+            ..AnsiEnvironment::default()
+        });
+        for attr in &self.attrs {
+            f.write_str(&creator.sgr_code(*attr))?;
+        }
+        f.write_str(self.text)?;
+        f.write_str(&creator.sgr_code(SgrAttribute::Reset))
+    }
+}
+
+/// Style strings inline: `"hi".bold()`, `"hi".red()`, `"hi".on_blue()`.
+///
+/// Implemented for `&str`, `&String` and [`Painted`], so calls can be chained.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::Paint;
+/// let warning = "careful".yellow().bold();
+/// assert_eq!(warning.to_string(), "\x1B[33m\x1B[1mcareful\x1B[0m");
+/// ```
+pub trait Paint<'a>: Sized {
+    /// Add an SGR attribute.
+    fn style(self, attr: SgrAttribute) -> Painted<'a>;
+
+    /// Set the foreground color.
+    fn fg(self, color: Color) -> Painted<'a> {
+        self.style(SgrAttribute::Foreground(color))
+    }
+    /// Set the background color.
+    fn on(self, color: Color) -> Painted<'a> {
+        self.style(SgrAttribute::Background(color))
+    }
+
+    /// Bold text.
+    fn bold(self) -> Painted<'a> {
+        self.style(SgrAttribute::Bold)
+    }
+    /// Faint text.
+    fn faint(self) -> Painted<'a> {
+        self.style(SgrAttribute::Faint)
+    }
+    /// Italic text.
+    fn italic(self) -> Painted<'a> {
+        self.style(SgrAttribute::Italic)
+    }
+    /// Underlined text.
+    fn underline(self) -> Painted<'a> {
+        self.style(SgrAttribute::Underline)
+    }
+    /// Swap foreground and background colors.
+    fn reverse(self) -> Painted<'a> {
+        self.style(SgrAttribute::Reverse)
+    }
+    /// Crossed-out text.
+    fn crossed_out(self) -> Painted<'a> {
+        self.style(SgrAttribute::CrossedOut)
+    }
+
+    /// Black foreground.
+    fn black(self) -> Painted<'a> {
+        self.fg(Color::Black)
+    }
+    /// Red foreground.
+    fn red(self) -> Painted<'a> {
+        self.fg(Color::Red)
+    }
+    /// Green foreground.
+    fn green(self) -> Painted<'a> {
+        self.fg(Color::Green)
+    }
+    /// Yellow foreground.
+    fn yellow(self) -> Painted<'a> {
+        self.fg(Color::Yellow)
+    }
+    /// Blue foreground.
+    fn blue(self) -> Painted<'a> {
+        self.fg(Color::Blue)
+    }
+    /// Magenta foreground.
+    fn magenta(self) -> Painted<'a> {
+        self.fg(Color::Magenta)
+    }
+    /// Cyan foreground.
+    fn cyan(self) -> Painted<'a> {
+        self.fg(Color::Cyan)
+    }
+    /// White foreground.
+    fn white(self) -> Painted<'a> {
+        self.fg(Color::White)
+    }
+
+    /// Black background.
+    fn on_black(self) -> Painted<'a> {
+        self.on(Color::Black)
+    }
+    /// Red background.
+    fn on_red(self) -> Painted<'a> {
+        self.on(Color::Red)
+    }
+    /// Green background.
+    fn on_green(self) -> Painted<'a> {
+        self.on(Color::Green)
+    }
+    /// Yellow background.
+    fn on_yellow(self) -> Painted<'a> {
+        self.on(Color::Yellow)
+    }
+    /// Blue background.
+    fn on_blue(self) -> Painted<'a> {
+        self.on(Color::Blue)
+    }
+    /// Magenta background.
+    fn on_magenta(self) -> Painted<'a> {
+        self.on(Color::Magenta)
+    }
+    /// Cyan background.
+    fn on_cyan(self) -> Painted<'a> {
+        self.on(Color::Cyan)
+    }
+    /// White background.
+    fn on_white(self) -> Painted<'a> {
+        self.on(Color::White)
+    }
+}
+
+impl<'a> Paint<'a> for &'a str {
+    fn style(self, attr: SgrAttribute) -> Painted<'a> {
+        Painted {
+            text: self,
+            attrs: vec![attr],
+        }
+    }
+}
+
+impl<'a> Paint<'a> for &'a String {
+    fn style(self, attr: SgrAttribute) -> Painted<'a> {
+        self.as_str().style(attr)
+    }
+}
+
+impl<'a> Paint<'a> for Painted<'a> {
+    fn style(mut self, attr: SgrAttribute) -> Painted<'a> {
+        self.attrs.push(attr);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_red_emits_code_and_reset() {
+        let s = format!("{}", "hi".red());
+        assert!(s.contains("\x1B[31m"));
+        assert!(s.ends_with("hi\x1B[0m"));
+    }
+
+    #[test]
+    fn test_chaining_and_string() {
+        let owned = "hi".to_string();
+        assert_eq!(
+            owned.on_blue().bold().to_string(),
+            "\x1B[44m\x1B[1mhi\x1B[0m"
+        );
+        assert_eq!(
+            "x".fg(Color::rgb(1, 2, 3)).attrs(),
+            &[SgrAttribute::Foreground(Color::rgb(1, 2, 3))]
+        );
+    }
+}
@@ -0,0 +1,591 @@
+//! ansi_render.rs
+//!
+//! Renderers that turn an [`AnsiParseResult`] into other text formats.
+
+use super::ansi_creator::AnsiCreator;
+use super::ansi_interpreter::{AnsiParseResult, AnsiSpan};
+use super::ansi_types::{
+    AnsiEscape, Color, CursorMove, DeviceControl, Erase, EraseMode, PromptMark, Scroll,
+    SgrAttribute, TabClear, TabOp, UnderlineKind,
+};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use unicode_width::UnicodeWidthChar;
+
+/// Render a parse result as HTML, wrapping each styled span in a `<span style="...">`.
+///
+/// The text is HTML-escaped and otherwise kept as is, so line breaks are only visible
+/// inside a `<pre>` element (or with `white-space: pre`). Offsets are expected in bytes
+/// (the default), not chars.
+///
+/// Colors are resolved with [`AnsiSpan::effective_colors`], so reverse video and
+/// concealed text are shown as a terminal would. The 16 named colors map to the 16
+/// HTML 4 color keywords (`maroon` for red, `red` for bright red, ...), 8-bit colors
+/// to xterm's default palette, and 24-bit colors to `#rrggbb`.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::parse_ansi_annotated;
+/// use ansi_escapers::render::to_html;
+/// let html = to_html(&parse_ansi_annotated("\x1B[1;91mfail\x1B[0m: a < b"));
+/// assert_eq!(
+///     html,
+///     "<span style=\"color:red;font-weight:bold\">fail</span>: a &lt; b"
+/// );
+/// ```
+pub fn to_html(result: &AnsiParseResult) -> String {
+    let mut spans: Vec<&AnsiSpan> = result.spans.iter().collect();
+    spans.sort_by_key(|span| span.start);
+
+    let mut out = String::with_capacity(result.text.len());
+    let mut pos = 0;
+    for span in spans {
+        let start = span.start.clamp(pos, result.text.len());
+        let end = span.end.clamp(start, result.text.len());
+        let (Some(before), Some(inner)) =
+            (result.text.get(pos..start), result.text.get(start..end))
+        else {
+            continue;
+        };
+        push_escaped(&mut out, before);
+        let style = css_style(span);
+        if style.is_empty() {
+            push_escaped(&mut out, inner);
+        } else {
+            out.push_str(&format!("<span style=\"{}\">", style));
+            push_escaped(&mut out, inner);
+            out.push_str("</span>");
+        }
+        pos = end;
+    }
+    push_escaped(&mut out, &result.text[pos..]);
+    out
+}
+
+/// Render a parse result as plain text with readable markers in place of escape codes.
+///
+/// Each span is wrapped in `[attr,attr,...]` and `[/]`, and each point is written as
+/// `<name>` or `<name:args>` at its position, e.g. `<cursor-up:2>` or
+/// `<erase-line:all>`. The output is deterministic, which makes it convenient for
+/// snapshot tests of styled output. Offsets are expected in bytes (the default).
+///
+/// Attributes are named after their effect (`bold`, `underline=curly`, ...). Colors use
+/// the named color (`fg=red`, `bg=bright-blue`), the palette index for 8-bit colors
+/// (`fg=196`), and `#rrggbb` for 24-bit colors.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::interpreter::parse_ansi_annotated;
+/// use ansi_escapers::render::to_markup;
+/// let result = parse_ansi_annotated("\x1B[1;31mhi\x1B[0m\x1B[2A there");
+/// assert_eq!(to_markup(&result), "[bold,fg=red]hi[/]<cursor-up:2> there");
+/// ```
+pub fn to_markup(result: &AnsiParseResult) -> String {
+    // (position, order at that position, marker): span ends, then points, then span starts
+    let mut markers: Vec<(usize, u8, String)> = Vec::new();
+    for span in &result.spans {
+        let attrs: Vec<String> = span.codes.iter().map(|code| markup_attr(*code)).collect();
+        markers.push((span.start, 2, format!("[{}]", attrs.join(","))));
+        markers.push((span.end, 0, "[/]".into()));
+    }
+    for point in &result.points {
+        markers.push((point.pos, 1, format!("<{}>", markup_escape(&point.code))));
+    }
+    markers.sort_by_key(|(pos, order, _)| (*pos, *order));
+
+    let mut out = String::with_capacity(result.text.len());
+    let mut last = 0;
+    for (pos, _, marker) in markers {
+        let pos = pos.clamp(last, result.text.len());
+        if let Some(text) = result.text.get(last..pos) {
+            out.push_str(text);
+            last = pos;
+        }
+        out.push_str(&marker);
+    }
+    out.push_str(result.text.get(last..).unwrap_or_default());
+    out
+}
+
+/// Produce the escape codes that turn a screen showing `prev` into one showing `next`.
+///
+/// Both results are taken to be drawn from the top-left corner (row 1, column 1), one
+/// screen line per `\n`-separated line of text; a `\r` before the `\n` belongs to the
+/// line break. Lines whose text and styling are unchanged are skipped. For a changed
+/// line the cursor is moved to the first difference and only the changed characters are
+/// rewritten; if the line got shorter, the rest of it is erased. Lines past the end of
+/// `next` are erased. Columns are display columns, so wide characters count as two.
+/// Points are not compared.
+///
+/// Attributes are reset after each rewritten run, so the output leaves no style active.
+/// Offsets are expected in bytes (the default).
+///
+/// # Example
+/// ```
+/// use ansi_escapers::{AnsiCreator, AnsiEnvironment};
+/// use ansi_escapers::interpreter::parse_ansi_annotated;
+/// use ansi_escapers::render::diff_escapes;
+/// let prev = parse_ansi_annotated("status: \x1B[31mfail\x1B[0m");
+/// let next = parse_ansi_annotated("status: \x1B[32mpass\x1B[0m");
+/// let creator = AnsiCreator::with_environment(AnsiEnvironment {
+///     supports_ansi: true,
+///     ..AnsiEnvironment::default()
+/// });
+/// let diff = diff_escapes(&prev, &next, &creator);
+/// assert_eq!(diff, "\x1B[1;9H\x1B[32mpass\x1B[0m");
+/// ```
+pub fn diff_escapes(
+    prev: &AnsiParseResult,
+    next: &AnsiParseResult,
+    creator: &AnsiCreator,
+) -> String {
+    let (old_lines, new_lines) = (styled_lines(prev), styled_lines(next));
+    let mut out = String::new();
+    for row in 0..old_lines.len().max(new_lines.len()) {
+        let old = old_lines.get(row).map_or(&[][..], Vec::as_slice);
+        let new = new_lines.get(row).map_or(&[][..], Vec::as_slice);
+        if old == new {
+            continue;
+        }
+        let first = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let mut suffix = old[first..]
+            .iter()
+            .rev()
+            .zip(new[first..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        // The unchanged tail can only stay in place if the changed part keeps its width
+        if cells_width(&old[first..old.len() - suffix])
+            != cells_width(&new[first..new.len() - suffix])
+        {
+            suffix = 0;
+        }
+
+        let to_u16 = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+        out.push_str(&creator.cursor_code(CursorMove::Position {
+            row: to_u16(row + 1),
+            col: to_u16(cells_width(&new[..first]) + 1),
+        }));
+        let mut current: &[SgrAttribute] = &[];
+        for &(ch, attrs) in &new[first..new.len() - suffix] {
+            if attrs != current {
+                if !current.is_empty() {
+                    out.push_str(&creator.sgr_code(SgrAttribute::Reset));
+                }
+                for attr in attrs {
+                    out.push_str(&creator.sgr_code(*attr));
+                }
+                current = attrs;
+            }
+            out.push(ch);
+        }
+        if !current.is_empty() {
+            out.push_str(&creator.sgr_code(SgrAttribute::Reset));
+        }
+        if suffix == 0 && cells_width(old) > cells_width(new) {
+            out.push_str(&creator.erase_code(Erase::Line(EraseMode::ToEnd)));
+        }
+    }
+    out
+}
+
+/// Split a result's text into lines of characters, each paired with its span's codes.
+fn styled_lines(result: &AnsiParseResult) -> Vec<Vec<(char, &[SgrAttribute])>> {
+    let mut spans: Vec<&AnsiSpan> = result.spans.iter().collect();
+    spans.sort_by_key(|span| span.start);
+    let mut spans = spans.into_iter().peekable();
+
+    let mut lines = vec![Vec::new()];
+    for (i, ch) in result.text.char_indices() {
+        while spans.next_if(|span| span.end <= i).is_some() {}
+        if ch == '\n' {
+            lines.push(Vec::new());
+            continue;
+        }
+        if ch == '\r' && result.text[i + 1..].starts_with('\n') {
+            continue;
+        }
+        let attrs = match spans.peek() {
+            Some(span) if span.start <= i => span.codes.as_slice(),
+            _ => &[],
+        };
+        if let Some(line) = lines.last_mut() {
+            line.push((ch, attrs));
+        }
+    }
+    lines
+}
+
+/// The display width of a run of styled characters.
+fn cells_width(cells: &[(char, &[SgrAttribute])]) -> usize {
+    cells.iter().map(|(ch, _)| ch.width().unwrap_or(0)).sum()
+}
+
+/// The markup name of an SGR attribute.
+fn markup_attr(attr: SgrAttribute) -> String {
+    let name = match attr {
+        SgrAttribute::Reset => "reset",
+        SgrAttribute::Bold => "bold",
+        SgrAttribute::Faint => "faint",
+        SgrAttribute::Italic => "italic",
+        SgrAttribute::Underline => "underline",
+        SgrAttribute::BlinkSlow => "blink",
+        SgrAttribute::BlinkRapid => "rapid-blink",
+        SgrAttribute::Reverse => "reverse",
+        SgrAttribute::Conceal => "conceal",
+        SgrAttribute::CrossedOut => "crossed-out",
+        SgrAttribute::Overline => "overline",
+        SgrAttribute::NotOverline => "no-overline",
+        SgrAttribute::NotBlinking => "no-blink",
+        SgrAttribute::Framed => "framed",
+        SgrAttribute::Encircled => "encircled",
+        SgrAttribute::NotFramedEncircled => "no-frame",
+        SgrAttribute::DefaultForeground => "fg=default",
+        SgrAttribute::DefaultBackground => "bg=default",
+        SgrAttribute::DefaultUnderlineColor => "ul=default",
+        SgrAttribute::Foreground(color) => return format!("fg={}", markup_color(color)),
+        SgrAttribute::Background(color) => return format!("bg={}", markup_color(color)),
+        SgrAttribute::UnderlineColor(color) => return format!("ul={}", markup_color(color)),
+        SgrAttribute::UnderlineStyle(kind) => match kind {
+            UnderlineKind::None => "underline=none",
+            UnderlineKind::Straight => "underline=straight",
+            UnderlineKind::Double => "underline=double",
+            UnderlineKind::Curly => "underline=curly",
+            UnderlineKind::Dotted => "underline=dotted",
+            UnderlineKind::Dashed => "underline=dashed",
+        },
+    };
+    name.into()
+}
+
+/// The markup name of a color.
+fn markup_color(color: Color) -> String {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::White => "white",
+        Color::BrightBlack => "bright-black",
+        Color::BrightRed => "bright-red",
+        Color::BrightGreen => "bright-green",
+        Color::BrightYellow => "bright-yellow",
+        Color::BrightBlue => "bright-blue",
+        Color::BrightMagenta => "bright-magenta",
+        Color::BrightCyan => "bright-cyan",
+        Color::BrightWhite => "bright-white",
+        Color::AnsiValue(idx) => return format!("{}", idx),
+        Color::Rgb24 { r, g, b } => return format!("#{:02x}{:02x}{:02x}", r, g, b),
+    };
+    name.into()
+}
+
+/// The markup for a point code, without the angle brackets.
+fn markup_escape(code: &AnsiEscape) -> String {
+    let join = |values: &[u16]| {
+        let values: Vec<String> = values.iter().map(|v| format!("{}", v)).collect();
+        values.join(",")
+    };
+    let erase_mode = |mode: EraseMode| match mode {
+        EraseMode::ToEnd => "to-end",
+        EraseMode::ToStart => "to-start",
+        EraseMode::All => "all",
+    };
+    match code {
+        AnsiEscape::Sgr(attr) => format!("sgr:{}", markup_attr(*attr)),
+        AnsiEscape::Cursor(movement) => match *movement {
+            CursorMove::Up(n) => format!("cursor-up:{}", n),
+            CursorMove::Down(n) => format!("cursor-down:{}", n),
+            CursorMove::Forward(n) => format!("cursor-forward:{}", n),
+            CursorMove::Backward(n) => format!("cursor-backward:{}", n),
+            CursorMove::NextLine(n) => format!("cursor-next-line:{}", n),
+            CursorMove::PreviousLine(n) => format!("cursor-previous-line:{}", n),
+            CursorMove::HorizontalAbsolute(n) => format!("cursor-column:{}", n),
+            CursorMove::Position { row, col } => format!("cursor-position:{},{}", row, col),
+            CursorMove::VerticalAbsolute(n) => format!("cursor-row:{}", n),
+            CursorMove::VerticalRelative(n) => format!("cursor-row-relative:{}", n),
+        },
+        AnsiEscape::Erase(Erase::Display(mode)) => format!("erase-display:{}", erase_mode(*mode)),
+        AnsiEscape::Erase(Erase::Line(mode)) => format!("erase-line:{}", erase_mode(*mode)),
+        AnsiEscape::Device(device) => match *device {
+            DeviceControl::SaveCursor => "save-cursor".into(),
+            DeviceControl::RestoreCursor => "restore-cursor".into(),
+            DeviceControl::HideCursor => "hide-cursor".into(),
+            DeviceControl::ShowCursor => "show-cursor".into(),
+            DeviceControl::SetCursorBlink(on) => format!("cursor-blink:{}", on),
+            DeviceControl::EnterAltScreen => "enter-alt-screen".into(),
+            DeviceControl::ExitAltScreen => "exit-alt-screen".into(),
+            DeviceControl::SetKittyKeyboard(flags) => format!("kitty-keyboard:{}", flags.bits()),
+            DeviceControl::PopKittyKeyboard(n) => format!("pop-kitty-keyboard:{}", n),
+            DeviceControl::SoftReset => "soft-reset".into(),
+            DeviceControl::LinuxPalette { index, r, g, b } => {
+                format!("linux-palette:{},#{:02x}{:02x}{:02x}", index, r, g, b)
+            }
+            DeviceControl::LinuxPaletteReset => "linux-palette-reset".into(),
+            DeviceControl::FullReset => "full-reset".into(),
+            DeviceControl::ReverseIndex => "reverse-index".into(),
+            DeviceControl::InitiateHighlightMouseTracking {
+                func,
+                start_col,
+                start_row,
+                first_row,
+                last_row,
+            } => format!(
+                "highlight-mouse-tracking:{}",
+                join(&[func, start_col, start_row, first_row, last_row])
+            ),
+            DeviceControl::SetCursorStyle(style) => format!("cursor-style:{}", style.param()),
+            DeviceControl::AltScreen { mode, enabled } => format!(
+                "alt-screen:{},{}",
+                mode.mode(),
+                if enabled { "on" } else { "off" }
+            ),
+        },
+        AnsiEscape::Tab(op) => match *op {
+            TabOp::Set => "tab-set".into(),
+            TabOp::Forward(n) => format!("tab-forward:{}", n),
+            TabOp::Backward(n) => format!("tab-backward:{}", n),
+            TabOp::Clear(TabClear::Current) => "tab-clear:current".into(),
+            TabOp::Clear(TabClear::All) => "tab-clear:all".into(),
+        },
+        AnsiEscape::Scroll(Scroll::Up(n)) => format!("scroll-up:{}", n),
+        AnsiEscape::Scroll(Scroll::Down(n)) => format!("scroll-down:{}", n),
+        AnsiEscape::CursorPositionReport { row, col } => {
+            format!("cursor-position-report:{},{}", row, col)
+        }
+        AnsiEscape::PrivateMode { mode, enabled } => {
+            format!(
+                "private-mode:{},{}",
+                mode,
+                if *enabled { "on" } else { "off" }
+            )
+        }
+        AnsiEscape::Mouse {
+            button,
+            col,
+            row,
+            pressed,
+        } => format!(
+            "mouse-{}:{}",
+            if *pressed { "press" } else { "release" },
+            join(&[*button, *col, *row])
+        ),
+        AnsiEscape::Repeat(n) => format!("repeat:{}", n),
+        AnsiEscape::Focus { gained: true } => "focus-in".into(),
+        AnsiEscape::Focus { gained: false } => "focus-out".into(),
+        AnsiEscape::WindowReport { kind, values } => {
+            format!("window-report:{};{}", kind, join(values))
+        }
+        AnsiEscape::ShellIntegration(mark) => match mark {
+            PromptMark::PromptStart => "prompt-start".into(),
+            PromptMark::CommandStart => "command-start".into(),
+            PromptMark::CommandExecuted => "command-executed".into(),
+            PromptMark::CommandFinished(Some(code)) => format!("command-finished:{}", code),
+            PromptMark::CommandFinished(None) => "command-finished".into(),
+        },
+        AnsiEscape::PasteStart => "paste-start".into(),
+        AnsiEscape::PasteEnd => "paste-end".into(),
+        AnsiEscape::FunctionKey(n) => format!("function-key:{}", n),
+        AnsiEscape::Unknown { params, final_byte } => {
+            format!("unknown:{}{}", params, *final_byte as char)
+        }
+    }
+}
+
+/// The CSS declarations for a span, separated by `;`.
+fn css_style(span: &AnsiSpan) -> String {
+    let mut decls = Vec::new();
+    let (fg, bg) = span.effective_colors();
+    if let Some(color) = fg {
+        decls.push(format!("color:{}", css_color(color)));
+    }
+    if let Some(color) = bg {
+        decls.push(format!("background-color:{}", css_color(color)));
+    }
+
+    let mut lines = Vec::new();
+    let mut underline_style = None;
+    for code in &span.codes {
+        match *code {
+            SgrAttribute::Bold => decls.push("font-weight:bold".into()),
+            SgrAttribute::Faint => decls.push("opacity:0.5".into()),
+            SgrAttribute::Italic => decls.push("font-style:italic".into()),
+            SgrAttribute::Underline => lines.push("underline"),
+            SgrAttribute::UnderlineStyle(kind) => {
+                let style = match kind {
+                    UnderlineKind::None => continue,
+                    UnderlineKind::Straight => "solid",
+                    UnderlineKind::Double => "double",
+                    UnderlineKind::Curly => "wavy",
+                    UnderlineKind::Dotted => "dotted",
+                    UnderlineKind::Dashed => "dashed",
+                };
+                lines.push("underline");
+                underline_style = Some(style);
+            }
+            SgrAttribute::UnderlineColor(color) => {
+                decls.push(format!("text-decoration-color:{}", css_color(color)))
+            }
+            SgrAttribute::CrossedOut => lines.push("line-through"),
+            SgrAttribute::Overline => lines.push("overline"),
+            // Concealed text with no background to match is hidden outright
+            SgrAttribute::Conceal if bg.is_none() => decls.push("visibility:hidden".into()),
+            _ => {}
+        }
+    }
+    if !lines.is_empty() {
+        decls.push(format!("text-decoration-line:{}", lines.join(" ")));
+    }
+    if let Some(style) = underline_style {
+        decls.push(format!("text-decoration-style:{}", style));
+    }
+    decls.join(";")
+}
+
+/// The CSS color for an ANSI color.
+fn css_color(color: Color) -> String {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "maroon",
+        Color::Green => "green",
+        Color::Yellow => "olive",
+        Color::Blue => "navy",
+        Color::Magenta => "purple",
+        Color::Cyan => "teal",
+        Color::White => "silver",
+        Color::BrightBlack => "gray",
+        Color::BrightRed => "red",
+        Color::BrightGreen => "lime",
+        Color::BrightYellow => "yellow",
+        Color::BrightBlue => "blue",
+        Color::BrightMagenta => "fuchsia",
+        Color::BrightCyan => "aqua",
+        Color::BrightWhite => "white",
+        Color::AnsiValue(_) | Color::Rgb24 { .. } => {
+            let (r, g, b) = color.to_rgb();
+            return format!("#{:02x}{:02x}{:02x}", r, g, b);
+        }
+    };
+    name.into()
+}
+
+/// Append `text` with the HTML special characters escaped.
+fn push_escaped(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi_escape::ansi_creator::AnsiEnvironment;
+    use crate::ansi_escape::ansi_interpreter::parse_ansi_annotated;
+
+    fn creator() -> AnsiCreator {
+        AnsiCreator::with_environment(AnsiEnvironment {
+            supports_ansi: true,
+            ..AnsiEnvironment::default()
+        })
+    }
+
+    #[test]
+    fn test_bold_red_span() {
+        let html = to_html(&parse_ansi_annotated("\x1B[1;31mhi\x1B[0m there"));
+        assert_eq!(
+            html,
+            "<span style=\"color:maroon;font-weight:bold\">hi</span> there"
+        );
+    }
+
+    #[test]
+    fn test_palette_and_truecolor() {
+        let html = to_html(&parse_ansi_annotated(
+            "\x1B[38;5;196;48;2;1;2;255mx\x1B[0m\x1B[4:3;58;5;21my",
+        ));
+        assert_eq!(
+            html,
+            "<span style=\"color:#ff0000;background-color:#0102ff\">x</span>\
+             <span style=\"text-decoration-color:#0000ff;\
+             text-decoration-line:underline;text-decoration-style:wavy\">y</span>"
+        );
+    }
+
+    #[test]
+    fn test_escapes_text_and_reverse() {
+        let html = to_html(&parse_ansi_annotated("<a href='x'>\x1B[7m&\x1B[0m"));
+        assert_eq!(html, "&lt;a href=&#39;x&#39;&gt;&amp;");
+        let html = to_html(&parse_ansi_annotated("\x1B[32;7mok"));
+        assert_eq!(html, "<span style=\"background-color:green\">ok</span>");
+    }
+
+    #[test]
+    fn test_diff_one_color_change() {
+        let creator = creator();
+        let prev = parse_ansi_annotated("header\nab\x1B[31mc\x1B[0mde\nfooter");
+        let next = parse_ansi_annotated("header\nab\x1B[32mc\x1B[0mde\nfooter");
+        let diff = diff_escapes(&prev, &next, &creator);
+        assert_eq!(diff, "\x1B[2;3H\x1B[32mc\x1B[0m");
+        assert!(diff.len() < next.render(&creator).len());
+        assert_eq!(diff_escapes(&next, &next, &creator), "");
+    }
+
+    #[test]
+    fn test_diff_shorter_lines_erased() {
+        let creator = creator();
+        let prev = parse_ansi_annotated("abc\nxyz");
+        let next = parse_ansi_annotated("ab");
+        assert_eq!(
+            diff_escapes(&prev, &next, &creator),
+            "\x1B[1;3H\x1B[0K\x1B[2;1H\x1B[0K"
+        );
+        // A width change shifts the rest of the line, so it is rewritten
+        let prev = parse_ansi_annotated("a\u{65e5}bc");
+        let next = parse_ansi_annotated("axbc");
+        assert_eq!(diff_escapes(&prev, &next, &creator), "\x1B[1;2Hxbc\x1B[0K");
+    }
+
+    #[test]
+    fn test_diff_crlf_line_breaks() {
+        let creator = creator();
+        let prev = parse_ansi_annotated("one\r\ntwo\r\n");
+        assert_eq!(
+            diff_escapes(&prev, &parse_ansi_annotated("one\ntwo\n"), &creator),
+            ""
+        );
+        let next = parse_ansi_annotated("one\r\ntwo!\r\n");
+        assert_eq!(diff_escapes(&prev, &next, &creator), "\x1B[2;4H!");
+    }
+
+    #[test]
+    fn test_markup_spans_and_colors() {
+        let result =
+            parse_ansi_annotated("\x1B[3;38;5;196mx\x1B[0m \x1B[4:3;48;2;1;2;255my\x1B[22mz");
+        assert_eq!(
+            to_markup(&result),
+            "[italic,fg=196]x[/] [bg=#0102ff,underline=curly]yz[/]"
+        );
+        assert_eq!(to_markup(&parse_ansi_annotated("plain")), "plain");
+    }
+
+    #[test]
+    fn test_markup_points() {
+        let result = parse_ansi_annotated("\x1B[?25la\x1B[1mb\x1B[2K\x1B[0m\x1B[3;4Hc");
+        assert_eq!(
+            to_markup(&result),
+            "<hide-cursor>a[bold]b[/]<erase-line:all><cursor-position:3,4>c"
+        );
+    }
+}
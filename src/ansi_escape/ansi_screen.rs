@@ -0,0 +1,308 @@
+//! ansi_screen.rs
+//!
+//! A minimal terminal screen model: applies parsed escape codes to a grid of
+//! styled cells, producing a snapshot that can be inspected or rendered.
+
+use super::ansi_interpreter::{AnsiParser, Event};
+use super::ansi_types::{
+    AnsiEscape, CursorMove, DeviceControl, Erase, EraseMode, SgrAttribute, Style, TabOp,
+};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Distance between the default tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// A single character cell on a [`Screen`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cell {
+    /// The character shown in the cell (a space for blank cells).
+    pub ch: char,
+    /// The SGR attributes the character was written with.
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            style: Style::new(),
+        }
+    }
+}
+
+/// A fixed-size grid of styled cells that ANSI output can be applied to.
+///
+/// Handles printable text, `\r`, `\n`, backspace and tab, cursor movement, erasing,
+/// cursor save/restore, REP, reverse index, full reset, and SGR. Other escape codes are
+/// ignored. `\n` moves to the start of the next line (as a terminal with `onlcr` set
+/// would display it), and the screen scrolls up when the cursor moves past the last row.
+/// Each character occupies one cell.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::screen::Screen;
+/// let mut screen = Screen::new(2, 10);
+/// screen.apply("\x1B[31mhello\x1B[0m\nworld");
+/// assert_eq!(screen.row_text(0), "hello");
+/// assert_eq!(screen.row_text(1), "world");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    rows: usize,
+    cols: usize,
+    cells: Vec<Cell>,
+    /// Cursor position as (row, col), 0-based. `col == cols` means a wrap is pending.
+    cursor: (usize, usize),
+    saved_cursor: Option<(usize, usize)>,
+    style: Style,
+    last_char: Option<char>,
+}
+
+impl Screen {
+    /// Create a blank screen with the given size. Zero dimensions are raised to one.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        Self {
+            rows,
+            cols,
+            cells: vec![Cell::default(); rows * cols],
+            cursor: (0, 0),
+            saved_cursor: None,
+            style: Style::new(),
+            last_char: None,
+        }
+    }
+
+    /// Number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The cursor position as (row, col), 0-based.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor.0, self.cursor.1.min(self.cols - 1))
+    }
+
+    /// The cell at the given 0-based position, or `None` if it is off the screen.
+    pub fn cell(&self, row: usize, col: usize) -> Option<&Cell> {
+        (row < self.rows && col < self.cols).then(|| &self.cells[row * self.cols + col])
+    }
+
+    /// The characters of a row, with trailing blanks trimmed.
+    pub fn row_text(&self, row: usize) -> String {
+        let start = row.min(self.rows) * self.cols;
+        let end = (start + self.cols).min(self.cells.len());
+        let text: String = self.cells[start..end].iter().map(|cell| cell.ch).collect();
+        text.trim_end_matches(' ').into()
+    }
+
+    /// Parse `input` and apply its text and escape codes to the screen.
+    pub fn apply(&mut self, input: &str) {
+        for event in AnsiParser::new(input).events() {
+            match event {
+                Event::Text(text) => text.chars().for_each(|ch| self.put_char(ch)),
+                Event::Escape(escape) => self.apply_escape(escape),
+            }
+        }
+    }
+
+    /// Apply a single escape code.
+    fn apply_escape(&mut self, escape: AnsiEscape) {
+        match escape {
+            AnsiEscape::Sgr(attr) => self.style = core::mem::take(&mut self.style).with(attr),
+            AnsiEscape::Cursor(movement) => self.move_cursor(movement),
+            AnsiEscape::Erase(erase) => self.erase(erase),
+            AnsiEscape::Device(DeviceControl::SaveCursor) => self.saved_cursor = Some(self.cursor),
+            AnsiEscape::Device(DeviceControl::RestoreCursor) => {
+                if let Some(cursor) = self.saved_cursor {
+                    self.cursor = cursor;
+                }
+            }
+            AnsiEscape::Device(DeviceControl::FullReset) => {
+                *self = Screen::new(self.rows, self.cols)
+            }
+            AnsiEscape::Device(DeviceControl::ReverseIndex) => self.reverse_index(),
+            AnsiEscape::Tab(TabOp::Forward(n)) => (0..n).for_each(|_| self.tab()),
+            AnsiEscape::Repeat(n) => {
+                if let Some(ch) = self.last_char {
+                    (0..n).for_each(|_| self.put_char(ch));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Write a character (or apply a control character) at the cursor.
+    fn put_char(&mut self, ch: char) {
+        match ch {
+            '\n' => {
+                self.cursor.1 = 0;
+                self.line_feed();
+            }
+            '\r' => self.cursor.1 = 0,
+            '\x08' => self.cursor.1 = self.cursor.1.min(self.cols - 1).saturating_sub(1),
+            '\t' => self.tab(),
+            _ if ch.is_control() => {}
+            _ => {
+                if self.cursor.1 >= self.cols {
+                    self.cursor.1 = 0;
+                    self.line_feed();
+                }
+                let (row, col) = self.cursor;
+                self.cells[row * self.cols + col] = Cell {
+                    ch,
+                    style: self.style.clone(),
+                };
+                self.cursor.1 += 1;
+                self.last_char = Some(ch);
+            }
+        }
+    }
+
+    /// Move down a row, scrolling the screen up if the cursor is on the last row.
+    fn line_feed(&mut self) {
+        if self.cursor.0 + 1 < self.rows {
+            self.cursor.0 += 1;
+        } else {
+            self.cells.drain(..self.cols);
+            self.cells.resize(self.rows * self.cols, Cell::default());
+        }
+    }
+
+    /// Move up a row, scrolling the screen down if the cursor is on the first row.
+    fn reverse_index(&mut self) {
+        self.cursor = self.cursor();
+        if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+        } else {
+            self.cells.truncate((self.rows - 1) * self.cols);
+            self.cells.splice(0..0, vec![Cell::default(); self.cols]);
+        }
+    }
+
+    /// Advance to the next tab stop, stopping at the last column.
+    fn tab(&mut self) {
+        let col = (self.cursor.1 / TAB_WIDTH + 1) * TAB_WIDTH;
+        self.cursor.1 = col.min(self.cols - 1);
+    }
+
+    /// Apply a cursor movement, clamping to the screen.
+    fn move_cursor(&mut self, movement: CursorMove) {
+        let (row, col) = self.cursor();
+        let max_row = self.rows - 1;
+        let max_col = self.cols - 1;
+        // Absolute positions are 1-based, with 0 treated as 1
+        let absolute = |n: u16, max: usize| (n.max(1) as usize - 1).min(max);
+        self.cursor = match movement {
+            CursorMove::Up(n) => (row.saturating_sub(n as usize), col),
+            CursorMove::Down(n) | CursorMove::VerticalRelative(n) => {
+                ((row + n as usize).min(max_row), col)
+            }
+            CursorMove::Forward(n) => (row, (col + n as usize).min(max_col)),
+            CursorMove::Backward(n) => (row, col.saturating_sub(n as usize)),
+            CursorMove::NextLine(n) => ((row + n as usize).min(max_row), 0),
+            CursorMove::PreviousLine(n) => (row.saturating_sub(n as usize), 0),
+            CursorMove::HorizontalAbsolute(n) => (row, absolute(n, max_col)),
+            CursorMove::VerticalAbsolute(n) => (absolute(n, max_row), col),
+            CursorMove::Position { row, col } => (absolute(row, max_row), absolute(col, max_col)),
+        };
+    }
+
+    /// Blank part of the display or the current line.
+    fn erase(&mut self, erase: Erase) {
+        let (row, col) = self.cursor();
+        let cursor = row * self.cols + col;
+        let line_start = row * self.cols;
+        let range = match erase {
+            Erase::Display(EraseMode::ToEnd) => cursor..self.cells.len(),
+            Erase::Display(EraseMode::ToStart) => 0..cursor + 1,
+            Erase::Display(EraseMode::All) => 0..self.cells.len(),
+            Erase::Line(EraseMode::ToEnd) => cursor..line_start + self.cols,
+            Erase::Line(EraseMode::ToStart) => line_start..cursor + 1,
+            Erase::Line(EraseMode::All) => line_start..line_start + self.cols,
+        };
+        self.cells[range].fill(Cell::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi_escape::ansi_types::Color;
+
+    #[test]
+    fn test_clear_then_home_writes_top_left() {
+        let mut screen = Screen::new(3, 5);
+        screen.apply("junk\njunk\njunk");
+        screen.apply("\x1B[2J\x1B[1;1Hhi");
+        assert_eq!(screen.cell(0, 0).unwrap().ch, 'h');
+        assert_eq!(screen.cell(0, 1).unwrap().ch, 'i');
+        assert_eq!(screen.row_text(0), "hi");
+        assert_eq!(screen.row_text(1), "");
+        assert_eq!(screen.row_text(2), "");
+        assert_eq!(screen.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn test_sgr_styles_cells() {
+        let mut screen = Screen::new(1, 10);
+        screen.apply("\x1B[1;31mab\x1B[0mc");
+        let bold_red = Style::new()
+            .with(SgrAttribute::Bold)
+            .with(SgrAttribute::Foreground(Color::Red));
+        assert_eq!(screen.cell(0, 0).unwrap().style, bold_red);
+        assert_eq!(screen.cell(0, 1).unwrap().style, bold_red);
+        assert!(screen.cell(0, 2).unwrap().style.is_empty());
+    }
+
+    #[test]
+    fn test_erase_line_to_end() {
+        let mut screen = Screen::new(1, 10);
+        screen.apply("abcdef\x1B[4G\x1B[K");
+        assert_eq!(screen.row_text(0), "abc");
+    }
+
+    #[test]
+    fn test_wrap_and_scroll() {
+        let mut screen = Screen::new(2, 3);
+        screen.apply("abcdefgh");
+        assert_eq!(screen.row_text(0), "def");
+        assert_eq!(screen.row_text(1), "gh");
+    }
+
+    #[test]
+    fn test_cursor_moves_are_clamped() {
+        let mut screen = Screen::new(3, 4);
+        screen.apply("\x1B[99;99Hx\x1B[99Ay\x1B[99Dz");
+        assert_eq!(screen.cell(2, 3).unwrap().ch, 'x');
+        assert_eq!(screen.cell(0, 3).unwrap().ch, 'y');
+        assert_eq!(screen.cell(0, 0).unwrap().ch, 'z');
+    }
+
+    #[test]
+    fn test_reverse_index_scrolls_down_at_top() {
+        let mut screen = Screen::new(2, 3);
+        screen.apply("ab\ncd\x1BMx\x1BMy");
+        assert_eq!(screen.row_text(0), "  y");
+        assert_eq!(screen.row_text(1), "abx");
+        screen.apply("\x1Bc");
+        assert_eq!(screen, Screen::new(2, 3));
+    }
+
+    #[test]
+    fn test_carriage_return_overwrites() {
+        let mut screen = Screen::new(1, 10);
+        screen.apply("hello\rj\x1B[2b");
+        assert_eq!(screen.row_text(0), "jjjlo");
+        screen.apply("\x1B[0b");
+        assert_eq!(screen.row_text(0), "jjjjo");
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Enums representing the full capability of ANSI escape codes,
 //! designed to make invalid states unrepresentable.
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
 /// Select Graphic Rendition (SGR) attributes for text formatting.
 /// Used to control style, color, and effects in ANSI escape codes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -26,14 +31,211 @@ pub enum SgrAttribute {
     Conceal,
     /// Crossed out (strikethrough) text.
     CrossedOut,
+    /// Overlined text.
+    Overline,
+    /// Turn off overline.
+    NotOverline,
+    /// Turn off blinking, slow or rapid.
+    NotBlinking,
+    /// Framed text.
+    Framed,
+    /// Encircled text.
+    Encircled,
+    /// Turn off framed and encircled.
+    NotFramedEncircled,
+    /// Reset the foreground color to the terminal default.
+    DefaultForeground,
+    /// Reset the background color to the terminal default.
+    DefaultBackground,
+    /// Reset the underline color to the terminal default (follows the foreground).
+    DefaultUnderlineColor,
     /// Set foreground color.
     Foreground(Color),
     /// Set background color.
     Background(Color),
     /// Set underline color.
     UnderlineColor(Color),
+    /// Underline with a specific style (`4:n`, colon sub-parameter form).
+    UnderlineStyle(UnderlineKind),
+}
+
+/// Underline styles selected with the `4:n` sub-parameter form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum UnderlineKind {
+    /// No underline (`4:0`).
+    None,
+    /// Single straight underline (`4:1`).
+    Straight,
+    /// Double underline (`4:2`).
+    Double,
+    /// Curly (wavy) underline (`4:3`).
+    Curly,
+    /// Dotted underline (`4:4`).
+    Dotted,
+    /// Dashed underline (`4:5`).
+    Dashed,
+}
+
+impl UnderlineKind {
+    /// The `n` in `4:n` for this style.
+    pub(crate) fn param(self) -> u8 {
+        match self {
+            UnderlineKind::None => 0,
+            UnderlineKind::Straight => 1,
+            UnderlineKind::Double => 2,
+            UnderlineKind::Curly => 3,
+            UnderlineKind::Dotted => 4,
+            UnderlineKind::Dashed => 5,
+        }
+    }
+
+    /// The style for the `n` in `4:n`, if it is a known style.
+    pub(crate) fn from_param(n: &str) -> Option<Self> {
+        Some(match n {
+            "0" => UnderlineKind::None,
+            "1" => UnderlineKind::Straight,
+            "2" => UnderlineKind::Double,
+            "3" => UnderlineKind::Curly,
+            "4" => UnderlineKind::Dotted,
+            "5" => UnderlineKind::Dashed,
+            _ => return None,
+        })
+    }
+}
+
+/// A set of active SGR attributes, holding at most one attribute of each kind
+/// (e.g. one foreground color).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    attrs: BTreeSet<SgrAttribute>,
+}
+
+impl Style {
+    /// Create an empty style.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an attribute, as a terminal would.
+    ///
+    /// The attribute replaces any attribute of the same kind. `Reset` clears the style, and
+    /// "off" attributes (`NotOverline`, `NotBlinking`, `NotFramedEncircled`,
+    /// `DefaultForeground`, `DefaultBackground`, `DefaultUnderlineColor` and
+    /// `UnderlineStyle(UnderlineKind::None)`) remove the attributes they cancel.
+    pub fn with(mut self, attr: SgrAttribute) -> Self {
+        match attr {
+            SgrAttribute::Reset => self.attrs.clear(),
+            _ if attr.is_off() => self.attrs.retain(|a| !attr.turns_off(a)),
+            _ => {
+                self.attrs.retain(|a| !a.same_kind(&attr));
+                self.attrs.insert(attr);
+            }
+        }
+        self
+    }
+
+    /// The active attributes, in a deterministic order.
+    pub fn attrs(&self) -> impl Iterator<Item = &SgrAttribute> {
+        self.attrs.iter()
+    }
+
+    /// True if no attributes are set.
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    /// True if going from `from` to `to` requires a full reset.
+    ///
+    /// A reset is needed when an attribute of `from` has no attribute of the same kind
+    /// in `to` (it is turned off rather than replaced) and there is no targeted code to
+    /// turn just that attribute off:
+    ///
+    /// * Overline is turned off with `NotOverline` (`55`), so no reset is needed.
+    /// * Blinking is turned off with `NotBlinking` (`25`), so no reset is needed.
+    /// * Framed and encircled are turned off with `NotFramedEncircled` (`54`), so no
+    ///   reset is needed.
+    /// * Foreground, background and underline colors are turned off with
+    ///   `DefaultForeground` (`39`), `DefaultBackground` (`49`) and
+    ///   `DefaultUnderlineColor` (`59`), so no reset is needed.
+    /// * Underline, plain or styled, is turned off with
+    ///   `UnderlineStyle(UnderlineKind::None)` (`4:0`), so no reset is needed.
+    /// * Every other attribute (bold, italic, reverse, ...) has no modelled disable code,
+    ///   so dropping it needs a reset.
+    ///
+    /// Replacing an attribute with another of the same kind (e.g. red to green) and adding
+    /// attributes never need a reset.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{Color, SgrAttribute, Style};
+    /// let red = Style::new().with(SgrAttribute::Foreground(Color::Red));
+    /// let bold_red = red.clone().with(SgrAttribute::Bold);
+    /// assert!(Style::reset_needed(&bold_red, &red));
+    /// assert!(!Style::reset_needed(&red, &bold_red));
+    /// ```
+    pub fn reset_needed(from: &Style, to: &Style) -> bool {
+        from.attrs.iter().any(|attr| {
+            !to.attrs.iter().any(|a| a.same_kind(attr))
+                && !OFF_ATTRIBUTES.iter().any(|off| off.turns_off(attr))
+        })
+    }
+}
+
+impl SgrAttribute {
+    /// True if both attributes set the same property (e.g. two foreground colors).
+    ///
+    /// `Underline` and `UnderlineStyle` share the underline slot.
+    pub(crate) fn same_kind(&self, other: &SgrAttribute) -> bool {
+        let is_underline = |a: &SgrAttribute| {
+            matches!(a, SgrAttribute::Underline | SgrAttribute::UnderlineStyle(_))
+        };
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+            || (is_underline(self) && is_underline(other))
+    }
+
+    /// True for "off" attributes, which cancel other attributes instead of setting one.
+    pub(crate) fn is_off(&self) -> bool {
+        OFF_ATTRIBUTES.contains(self)
+    }
+
+    /// True if this "off" attribute cancels `other`.
+    pub(crate) fn turns_off(&self, other: &SgrAttribute) -> bool {
+        matches!(
+            (self, other),
+            (SgrAttribute::NotOverline, SgrAttribute::Overline)
+                | (
+                    SgrAttribute::NotBlinking,
+                    SgrAttribute::BlinkSlow | SgrAttribute::BlinkRapid
+                )
+                | (
+                    SgrAttribute::NotFramedEncircled,
+                    SgrAttribute::Framed | SgrAttribute::Encircled
+                )
+                | (SgrAttribute::DefaultForeground, SgrAttribute::Foreground(_))
+                | (SgrAttribute::DefaultBackground, SgrAttribute::Background(_))
+                | (
+                    SgrAttribute::DefaultUnderlineColor,
+                    SgrAttribute::UnderlineColor(_)
+                )
+                | (
+                    SgrAttribute::UnderlineStyle(UnderlineKind::None),
+                    SgrAttribute::Underline | SgrAttribute::UnderlineStyle(_)
+                )
+        )
+    }
 }
 
+/// Attributes that turn off another attribute rather than setting one.
+const OFF_ATTRIBUTES: [SgrAttribute; 7] = [
+    SgrAttribute::NotOverline,
+    SgrAttribute::NotBlinking,
+    SgrAttribute::NotFramedEncircled,
+    SgrAttribute::DefaultForeground,
+    SgrAttribute::DefaultBackground,
+    SgrAttribute::DefaultUnderlineColor,
+    SgrAttribute::UnderlineStyle(UnderlineKind::None),
+];
+
 /// Color specification for ANSI codes, supporting standard, 8-bit, and 24-bit colors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Color {
@@ -75,6 +277,206 @@ pub enum Color {
     Rgb24 { r: u8, g: u8, b: u8 },
 }
 
+/// Which color slot a [`Color`] is applied to, for [`Color::sgr_params`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorRole {
+    /// Text color (`30`-`37`, `90`-`97`, `38`).
+    Foreground,
+    /// Background color (`40`-`47`, `100`-`107`, `48`).
+    Background,
+    /// Underline color (`58`).
+    Underline,
+}
+
+impl Color {
+    /// Create a 24-bit RGB color.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    /// assert_eq!(Color::rgb(255, 136, 0), Color::Rgb24 { r: 255, g: 136, b: 0 });
+    /// ```
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color::Rgb24 { r, g, b }
+    }
+
+    /// Parse a hex color in the `#rrggbb` or `#rgb` form (digits are case-insensitive).
+    ///
+    /// In the short form each digit is doubled, so `#f80` is `#ff8800`.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    /// assert_eq!(Color::hex("#ff8800"), Ok(Color::rgb(255, 136, 0)));
+    /// assert_eq!(Color::hex("#f80"), Ok(Color::rgb(255, 136, 0)));
+    /// assert!(Color::hex("#zzz").is_err());
+    /// ```
+    pub fn hex(s: &str) -> Result<Color, ColorParseError> {
+        let digits = s.strip_prefix('#').ok_or(ColorParseError::MissingHash)?;
+        let mut values = [0u8; 6];
+        for (value, ch) in values.iter_mut().zip(digits.chars()) {
+            *value = ch.to_digit(16).ok_or(ColorParseError::InvalidDigit(ch))? as u8;
+        }
+        match digits.len() {
+            6 => Ok(Color::rgb(
+                values[0] << 4 | values[1],
+                values[2] << 4 | values[3],
+                values[4] << 4 | values[5],
+            )),
+            3 => Ok(Color::rgb(values[0] * 17, values[1] * 17, values[2] * 17)),
+            _ => Err(ColorParseError::InvalidLength),
+        }
+    }
+
+    /// The 16 named colors in palette order (`Black` through `BrightWhite`).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    /// assert_eq!(Color::standard_16()[9], Color::BrightRed);
+    /// ```
+    pub fn standard_16() -> [Color; 16] {
+        BASIC16_RGB.map(|(color, _)| color)
+    }
+
+    /// All 256 palette colors, as `AnsiValue(0)` through `AnsiValue(255)`.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    /// assert_eq!(Color::all_256().last(), Some(Color::AnsiValue(255)));
+    /// ```
+    pub fn all_256() -> impl Iterator<Item = Color> {
+        (0..=u8::MAX).map(Color::AnsiValue)
+    }
+
+    /// Map this color to the nearest index in the xterm 256-color palette.
+    ///
+    /// Named colors return their palette index (0-15) and 8-bit colors are returned
+    /// unchanged. 24-bit colors pick whichever is closer of the 6x6x6 color cube
+    /// (16-231) and the grayscale ramp (232-255).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    ///
+    /// assert_eq!(Color::Rgb24 { r: 255, g: 0, b: 0 }.to_ansi256(), 196);
+    /// assert_eq!(Color::BrightRed.to_ansi256(), 9);
+    /// ```
+    pub fn to_ansi256(self) -> u8 {
+        match self {
+            Color::AnsiValue(idx) => idx,
+            Color::Rgb24 { r, g, b } => rgb_to_ansi256(r, g, b),
+            named => BASIC16_RGB
+                .iter()
+                .position(|(color, _)| *color == named)
+                .map(|idx| idx as u8)
+                .unwrap_or(7),
+        }
+    }
+
+    /// The SGR parameters that set this color in the given role.
+    ///
+    /// Named colors use the compact codes (`31`, `44`, `91`, ...) as foreground or
+    /// background. Underline colors have no compact form, so named colors use their
+    /// palette index (`58;5;n`). 8-bit colors use `38;5;n` and 24-bit colors `38;2;r;g;b`
+    /// (with `48` or `58` for the other roles).
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{Color, ColorRole};
+    /// assert_eq!(Color::Red.sgr_params(ColorRole::Foreground), vec![31]);
+    /// assert_eq!(Color::AnsiValue(208).sgr_params(ColorRole::Background), vec![48, 5, 208]);
+    /// assert_eq!(Color::rgb(1, 2, 3).sgr_params(ColorRole::Underline), vec![58, 2, 1, 2, 3]);
+    /// ```
+    pub fn sgr_params(&self, role: ColorRole) -> Vec<u16> {
+        let base = match role {
+            ColorRole::Foreground => 38,
+            ColorRole::Background => 48,
+            ColorRole::Underline => 58,
+        };
+        match *self {
+            Color::Rgb24 { r, g, b } => vec![base, 2, r.into(), g.into(), b.into()],
+            Color::AnsiValue(idx) => vec![base, 5, idx.into()],
+            named if role == ColorRole::Underline => vec![base, 5, named.to_ansi256().into()],
+            named => {
+                let idx = u16::from(named.to_ansi256());
+                // 30-37 and 90-97 for the foreground, 10 higher for the background
+                let code = if idx < 8 { 30 + idx } else { 82 + idx };
+                vec![if role == ColorRole::Background {
+                    code + 10
+                } else {
+                    code
+                }]
+            }
+        }
+    }
+
+    /// The RGB value of this color, using xterm's default palette for named and 8-bit colors.
+    pub(crate) fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb24 { r, g, b } => (r, g, b),
+            other => ansi256_to_rgb(other.to_ansi256()),
+        }
+    }
+
+    /// Map this color to the nearest of the 16 named colors.
+    ///
+    /// Named colors are returned unchanged. 8-bit and 24-bit colors are compared against
+    /// xterm's default palette using a perceptually weighted ("redmean") RGB distance.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Color;
+    ///
+    /// assert_eq!(Color::Rgb24 { r: 80, g: 80, b: 80 }.to_basic16(), Color::BrightBlack);
+    /// ```
+    pub fn to_basic16(self) -> Color {
+        let rgb = match self {
+            Color::AnsiValue(idx) => ansi256_to_rgb(idx),
+            Color::Rgb24 { r, g, b } => (r, g, b),
+            named => return named,
+        };
+        BASIC16_RGB
+            .iter()
+            .min_by_key(|(_, named)| perceptual_distance(rgb, *named))
+            .map(|(color, _)| *color)
+            .unwrap_or(Color::White)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    /// Convert an `(r, g, b)` tuple into a 24-bit color.
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Color::rgb(r, g, b)
+    }
+}
+
+/// Error returned by [`Color::hex`] for a malformed hex color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorParseError {
+    /// The string does not start with `#`.
+    MissingHash,
+    /// The string has a character that is not a hex digit.
+    InvalidDigit(char),
+    /// The string does not have exactly 3 or 6 hex digits after the `#`.
+    InvalidLength,
+}
+
+impl core::fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ColorParseError::MissingHash => write!(f, "hex color must start with '#'"),
+            ColorParseError::InvalidDigit(ch) => write!(f, "invalid hex digit {:?}", ch),
+            ColorParseError::InvalidLength => {
+                write!(f, "hex color must have 3 or 6 digits after '#'")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ColorParseError {}
+
 /// Cursor movement commands for ANSI escape codes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CursorMove {
@@ -94,6 +496,41 @@ pub enum CursorMove {
     HorizontalAbsolute(u16),
     /// Move cursor to specific row and column.
     Position { row: u16, col: u16 },
+    /// Move cursor to absolute vertical position (row), keeping the column (VPA).
+    VerticalAbsolute(u16),
+    /// Move cursor down by `u16` rows, keeping the column (VPR).
+    VerticalRelative(u16),
+}
+
+/// Tab stop operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TabOp {
+    /// Set a tab stop at the current column (HTS, `ESC H`).
+    Set,
+    /// Move the cursor forward `u16` tab stops (CHT, `ESC [ n I`).
+    Forward(u16),
+    /// Move the cursor backward `u16` tab stops (CBT, `ESC [ n Z`).
+    Backward(u16),
+    /// Clear tab stops (TBC, `ESC [ n g`).
+    Clear(TabClear),
+}
+
+/// Which tab stops a [`TabOp::Clear`] removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TabClear {
+    /// Clear the tab stop at the current column (`ESC [ 0 g`).
+    Current,
+    /// Clear all tab stops (`ESC [ 3 g`).
+    All,
+}
+
+/// Scroll commands: move the page contents within the scrolling region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scroll {
+    /// Scroll up by `u16` lines (SU, `ESC [ n S`); new blank lines appear at the bottom.
+    Up(u16),
+    /// Scroll down by `u16` lines (SD, `ESC [ n T`); new blank lines appear at the top.
+    Down(u16),
 }
 
 /// Erase display or line commands for clearing parts of the terminal.
@@ -119,14 +556,330 @@ pub enum EraseMode {
 /// Device control commands for cursor and terminal state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DeviceControl {
-    /// Save the current cursor position.
+    /// Save the current cursor position (`ESC [ s`, or DECSC `ESC 7` when parsing).
     SaveCursor,
-    /// Restore the saved cursor position.
+    /// Restore the saved cursor position (`ESC [ u`, or DECRC `ESC 8` when parsing).
     RestoreCursor,
     /// Hide the cursor.
     HideCursor,
     /// Show the cursor.
     ShowCursor,
+    /// Enable (`true`) or disable (`false`) cursor blinking.
+    SetCursorBlink(bool),
+    /// Switch to the alternate screen buffer (private mode 1049 set).
+    EnterAltScreen,
+    /// Switch back from the alternate screen buffer (private mode 1049 reset).
+    ExitAltScreen,
+    /// Enable the Kitty keyboard protocol with the given flags (`ESC [ > flags u`).
+    SetKittyKeyboard(KittyKeyboardFlags),
+    /// Pop `u16` entries from the Kitty keyboard protocol stack (`ESC [ < n u`).
+    PopKittyKeyboard(u16),
+    /// Soft terminal reset (DECSTR, `ESC [ ! p`).
+    SoftReset,
+    /// Set Linux console palette entry `index` (0-15) to an RGB color (`ESC ] P n rr gg bb`).
+    LinuxPalette { index: u8, r: u8, g: u8, b: u8 },
+    /// Reset the Linux console palette to its defaults (`ESC ] R`).
+    LinuxPaletteReset,
+    /// Full terminal reset (RIS, `ESC c`).
+    FullReset,
+    /// Move the cursor up one line, scrolling down at the top margin (RI, `ESC M`).
+    ReverseIndex,
+    /// Start highlight mouse tracking
+    /// (`ESC [ func ; start_col ; start_row ; first_row ; last_row T`).
+    ///
+    /// Shares the `T` final byte with [`Scroll::Down`]; the five parameters tell them apart.
+    InitiateHighlightMouseTracking {
+        func: u16,
+        start_col: u16,
+        start_row: u16,
+        first_row: u16,
+        last_row: u16,
+    },
+    /// Set the cursor shape (DECSCUSR, `ESC [ n SP q`).
+    SetCursorStyle(CursorStyle),
+    /// Switch to (`enabled`) or back from the alternate screen with private mode 47 or
+    /// 1047 (`ESC [ ? mode h/l`).
+    ///
+    /// Mode 1049 is parsed as [`DeviceControl::EnterAltScreen`] and
+    /// [`DeviceControl::ExitAltScreen`] instead.
+    AltScreen { mode: AltScreenMode, enabled: bool },
+}
+
+impl DeviceControl {
+    /// The alternate screen mode this command switches, if it is an alternate screen switch.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::{AltScreenMode, DeviceControl};
+    /// let mode = DeviceControl::EnterAltScreen.alt_screen_mode().unwrap();
+    /// assert_eq!(mode, AltScreenMode::SaveCursor);
+    /// assert!(mode.saves_cursor());
+    /// assert_eq!(DeviceControl::HideCursor.alt_screen_mode(), None);
+    /// ```
+    pub fn alt_screen_mode(&self) -> Option<AltScreenMode> {
+        match *self {
+            DeviceControl::EnterAltScreen | DeviceControl::ExitAltScreen => {
+                Some(AltScreenMode::SaveCursor)
+            }
+            DeviceControl::AltScreen { mode, .. } => Some(mode),
+            _ => None,
+        }
+    }
+}
+
+/// The private modes that switch to the alternate screen buffer, which differ in what
+/// happens to the cursor and the alternate screen's contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AltScreenMode {
+    /// Mode 47: switch buffers only.
+    Plain,
+    /// Mode 1047: switch buffers, clearing the alternate screen when switching back.
+    ClearOnExit,
+    /// Mode 1049: save the cursor (as DECSC) and switch to a cleared alternate screen;
+    /// the cursor is restored when switching back.
+    SaveCursor,
+}
+
+impl AltScreenMode {
+    /// The private mode number.
+    pub fn mode(self) -> u16 {
+        match self {
+            AltScreenMode::Plain => 47,
+            AltScreenMode::ClearOnExit => 1047,
+            AltScreenMode::SaveCursor => 1049,
+        }
+    }
+
+    /// The mode for a private mode number, if it is an alternate screen mode.
+    pub(crate) fn from_mode(mode: u16) -> Option<Self> {
+        Some(match mode {
+            47 => AltScreenMode::Plain,
+            1047 => AltScreenMode::ClearOnExit,
+            1049 => AltScreenMode::SaveCursor,
+            _ => return None,
+        })
+    }
+
+    /// Whether entering saves the cursor position and leaving restores it.
+    pub fn saves_cursor(self) -> bool {
+        matches!(self, AltScreenMode::SaveCursor)
+    }
+
+    /// Whether the alternate screen is cleared when switching back to the main screen.
+    pub fn clears_on_exit(self) -> bool {
+        matches!(self, AltScreenMode::ClearOnExit)
+    }
+}
+
+/// A navigation or function key, as sent by a terminal when the key is pressed.
+///
+/// Produced by [`parse_key`](crate::interpreter::parse_key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// Up arrow (`ESC [ A` or `ESC O A`).
+    Up,
+    /// Down arrow (`ESC [ B` or `ESC O B`).
+    Down,
+    /// Right arrow (`ESC [ C` or `ESC O C`).
+    Right,
+    /// Left arrow (`ESC [ D` or `ESC O D`).
+    Left,
+    /// Home (`ESC [ H`, `ESC O H`, `ESC [ 1 ~` or `ESC [ 7 ~`).
+    Home,
+    /// End (`ESC [ F`, `ESC O F`, `ESC [ 4 ~` or `ESC [ 8 ~`).
+    End,
+    /// Insert (`ESC [ 2 ~`).
+    Insert,
+    /// Delete (`ESC [ 3 ~`).
+    Delete,
+    /// Page up (`ESC [ 5 ~`).
+    PageUp,
+    /// Page down (`ESC [ 6 ~`).
+    PageDown,
+    /// Function key F1-F12 (`ESC O P`..`ESC O S`, `ESC [ 11 ~`..`ESC [ 24 ~`).
+    F(u8),
+}
+
+/// Modifier keys held with a key, decoded from xterm's modifier parameter.
+///
+/// The parameter is one more than a bitmask of shift (1), alt (2), ctrl (4) and meta (8),
+/// so `ESC [ 1 ; 5 C` is Ctrl+Right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    /// Shift is held.
+    pub shift: bool,
+    /// Alt (Option) is held.
+    pub alt: bool,
+    /// Ctrl is held.
+    pub ctrl: bool,
+    /// Meta is held.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// Decode xterm's modifier parameter (e.g. `5` for ctrl). `0` and `1` mean none.
+    ///
+    /// # Example
+    /// ```
+    /// use ansi_escapers::Modifiers;
+    /// let mods = Modifiers::from_param(7);
+    /// assert!(mods.ctrl && mods.alt && !mods.shift);
+    /// ```
+    pub fn from_param(param: u16) -> Self {
+        let bits = param.saturating_sub(1);
+        Self {
+            shift: bits & 1 != 0,
+            alt: bits & 2 != 0,
+            ctrl: bits & 4 != 0,
+            meta: bits & 8 != 0,
+        }
+    }
+
+    /// Encode as xterm's modifier parameter; `1` when no modifier is held.
+    pub fn to_param(self) -> u16 {
+        1 + u16::from(self.shift)
+            + 2 * u16::from(self.alt)
+            + 4 * u16::from(self.ctrl)
+            + 8 * u16::from(self.meta)
+    }
+
+    /// True if no modifier is held.
+    pub fn is_empty(self) -> bool {
+        self == Self::default()
+    }
+}
+
+/// Progressive enhancement flags of the Kitty keyboard protocol (`ESC [ > flags u`).
+///
+/// Flags combine with `|`. Bits without a named constant are kept as they were parsed.
+///
+/// # Example
+/// ```
+/// use ansi_escapers::KittyKeyboardFlags;
+/// let mut flags = KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES;
+/// flags.insert(KittyKeyboardFlags::REPORT_EVENT_TYPES);
+/// assert_eq!(flags.bits(), 3);
+/// assert!(flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KittyKeyboardFlags(u16);
+
+impl KittyKeyboardFlags {
+    /// Report Esc, Alt+key and Ctrl+key in an unambiguous form (`1`).
+    pub const DISAMBIGUATE_ESCAPE_CODES: Self = Self(1);
+    /// Report key repeat and release events as well as presses (`2`).
+    pub const REPORT_EVENT_TYPES: Self = Self(2);
+    /// Report shifted and base layout keys alongside the key (`4`).
+    pub const REPORT_ALTERNATE_KEYS: Self = Self(4);
+    /// Report every key, including text keys, as an escape code (`8`).
+    pub const REPORT_ALL_KEYS_AS_ESCAPE_CODES: Self = Self(8);
+    /// Report the text a key produces along with the key (`16`).
+    pub const REPORT_ASSOCIATED_TEXT: Self = Self(16);
+
+    /// No flags set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Flags from their numeric form, keeping unknown bits.
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    /// The numeric form used in the escape code.
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// True if every flag in `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Set the flags in `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Clear the flags in `other`.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl core::ops::BitOr for KittyKeyboardFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl core::ops::BitOrAssign for KittyKeyboardFlags {
+    fn bitor_assign(&mut self, other: Self) {
+        self.insert(other);
+    }
+}
+
+/// Cursor shapes selected with DECSCUSR (`ESC [ n SP q`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorStyle {
+    /// The terminal's default shape (`0`).
+    Default,
+    /// Blinking block (`1`).
+    BlinkingBlock,
+    /// Steady block (`2`).
+    SteadyBlock,
+    /// Blinking underline (`3`).
+    BlinkingUnderline,
+    /// Steady underline (`4`).
+    SteadyUnderline,
+    /// Blinking bar (`5`).
+    BlinkingBar,
+    /// Steady bar (`6`).
+    SteadyBar,
+}
+
+impl CursorStyle {
+    /// The `n` in `ESC [ n SP q` for this style.
+    pub(crate) fn param(self) -> u8 {
+        match self {
+            CursorStyle::Default => 0,
+            CursorStyle::BlinkingBlock => 1,
+            CursorStyle::SteadyBlock => 2,
+            CursorStyle::BlinkingUnderline => 3,
+            CursorStyle::SteadyUnderline => 4,
+            CursorStyle::BlinkingBar => 5,
+            CursorStyle::SteadyBar => 6,
+        }
+    }
+
+    /// The style for the `n` in `ESC [ n SP q`, if it is a known style.
+    pub(crate) fn from_param(n: u16) -> Option<Self> {
+        Some(match n {
+            0 => CursorStyle::Default,
+            1 => CursorStyle::BlinkingBlock,
+            2 => CursorStyle::SteadyBlock,
+            3 => CursorStyle::BlinkingUnderline,
+            4 => CursorStyle::SteadyUnderline,
+            5 => CursorStyle::BlinkingBar,
+            6 => CursorStyle::SteadyBar,
+            _ => return None,
+        })
+    }
+}
+
+/// Shell integration prompt marker (FinalTerm / iTerm2 OSC 133, `ESC ] 133 ; mark ST`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptMark {
+    /// Start of the prompt (`A`).
+    PromptStart,
+    /// End of the prompt, where the user starts typing a command (`B`).
+    CommandStart,
+    /// The command was submitted and its output begins (`C`).
+    CommandExecuted,
+    /// The command finished (`D`), with its exit code if the shell reported one (`D ; code`).
+    CommandFinished(Option<i32>),
 }
 
 /// The top-level enum representing any ANSI escape code supported by this library.
@@ -140,5 +893,473 @@ pub enum AnsiEscape {
     Erase(Erase),
     /// Device control command.
     Device(DeviceControl),
+    /// Tab stop operation.
+    Tab(TabOp),
+    /// Scroll the page up or down.
+    Scroll(Scroll),
+    /// Cursor position report (`ESC [ row ; col R`), sent by the terminal in reply to `ESC [ 6 n`.
+    CursorPositionReport { row: u16, col: u16 },
+    /// DEC private mode set (`ESC [ ? mode h`) or reset (`ESC [ ? mode l`).
+    ///
+    /// Cursor visibility (mode 25), blinking (mode 12), and the alternate screen (modes 47,
+    /// 1047 and 1049) are reported as [`DeviceControl`] instead.
+    PrivateMode { mode: u16, enabled: bool },
+    /// SGR mouse event (`ESC [ < button ; col ; row M` for press, `m` for release).
+    Mouse {
+        button: u16,
+        col: u16,
+        row: u16,
+        pressed: bool,
+    },
+    /// Repeat the preceding character `u16` times (REP, `ESC [ n b`). A count of 0 is
+    /// parsed as 1.
+    ///
+    /// `AnsiParser::parse_annotated` expands this into the cleaned text instead of
+    /// reporting it as a point.
+    Repeat(u16),
+    /// Focus event (`ESC [ I` when the terminal gains focus, `ESC [ O` when it loses it),
+    /// sent while focus reporting (private mode 1004) is enabled.
+    Focus { gained: bool },
+    /// Window operation report (`ESC [ kind ; values... t`), e.g. `ESC [ 8 ; rows ; cols t`
+    /// in reply to the text area size request `ESC [ 18 t`.
+    WindowReport { kind: u16, values: Vec<u16> },
+    /// Shell integration marker (OSC 133), terminated by BEL or ST.
+    ShellIntegration(PromptMark),
+    /// Start of pasted text (`ESC [ 200 ~`), sent while bracketed paste (private mode 2004)
+    /// is enabled.
+    PasteStart,
+    /// End of pasted text (`ESC [ 201 ~`).
+    PasteEnd,
+    /// A key sent as `ESC [ n ~` other than the paste markers, e.g. `3` for Delete or
+    /// `15` for F5.
+    FunctionKey(u16),
+    /// A CSI sequence this library does not model, kept verbatim (`ESC [ params final_byte`).
+    ///
+    /// Only produced when the parser is configured to preserve unknown sequences.
+    Unknown { params: String, final_byte: u8 },
     // Extend with more ANSI capabilities as needed
 }
+
+impl From<SgrAttribute> for AnsiEscape {
+    fn from(attr: SgrAttribute) -> Self {
+        AnsiEscape::Sgr(attr)
+    }
+}
+
+impl From<CursorMove> for AnsiEscape {
+    fn from(movement: CursorMove) -> Self {
+        AnsiEscape::Cursor(movement)
+    }
+}
+
+impl From<Erase> for AnsiEscape {
+    fn from(erase: Erase) -> Self {
+        AnsiEscape::Erase(erase)
+    }
+}
+
+impl From<DeviceControl> for AnsiEscape {
+    fn from(device: DeviceControl) -> Self {
+        AnsiEscape::Device(device)
+    }
+}
+
+/// RGB values of the 16 named colors, in xterm's default palette order.
+const BASIC16_RGB: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::BrightBlack, (127, 127, 127)),
+    (Color::BrightRed, (255, 0, 0)),
+    (Color::BrightGreen, (0, 255, 0)),
+    (Color::BrightYellow, (255, 255, 0)),
+    (Color::BrightBlue, (92, 92, 255)),
+    (Color::BrightMagenta, (255, 0, 255)),
+    (Color::BrightCyan, (0, 255, 255)),
+    (Color::BrightWhite, (255, 255, 255)),
+];
+
+/// Channel levels used by the 6x6x6 color cube (indices 16-231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Helper to compute the squared distance between two RGB colors.
+fn rgb_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+/// Helper to map an RGB color to the nearest xterm 256-color palette index.
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| -> u8 {
+        match v {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (v - 35) / 40,
+        }
+    };
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (
+        CUBE_LEVELS[ri as usize],
+        CUBE_LEVELS[gi as usize],
+        CUBE_LEVELS[bi as usize],
+    );
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let gray_index = if avg > 238 {
+        23
+    } else {
+        avg.saturating_sub(3) / 10
+    };
+    let gray_level = 8 + 10 * gray_index;
+    let gray = (gray_level, gray_level, gray_level);
+    if rgb_distance((r, g, b), gray) < rgb_distance((r, g, b), cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+/// Helper to compute a perceptually weighted ("redmean") squared distance between two RGB colors.
+fn perceptual_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let rmean = (a.0 as i32 + b.0 as i32) / 2;
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (((512 + rmean) * dr * dr) / 256 + 4 * dg * dg + ((767 - rmean) * db * db) / 256) as u32
+}
+
+/// Helper to convert an xterm 256-color palette index to its RGB value.
+fn ansi256_to_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => BASIC16_RGB[idx as usize].1,
+        16..=231 => {
+            let i = idx - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[((i / 6) % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (idx - 232);
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_color_hex_long_form() {
+        assert_eq!(
+            Color::hex("#ff8800"),
+            Ok(Color::Rgb24 {
+                r: 255,
+                g: 136,
+                b: 0
+            })
+        );
+        assert_eq!(Color::hex("#FF8800"), Color::hex("#ff8800"));
+    }
+
+    #[test]
+    fn test_color_hex_short_form() {
+        assert_eq!(Color::hex("#f80"), Ok(Color::rgb(255, 136, 0)));
+        assert_eq!(Color::hex("#000"), Ok(Color::rgb(0, 0, 0)));
+    }
+
+    #[test]
+    fn test_color_hex_rejects_malformed() {
+        assert_eq!(Color::hex("#zzz"), Err(ColorParseError::InvalidDigit('z')));
+        assert_eq!(Color::hex("ff8800"), Err(ColorParseError::MissingHash));
+        assert_eq!(Color::hex("#ff88"), Err(ColorParseError::InvalidLength));
+        assert_eq!(Color::hex("#"), Err(ColorParseError::InvalidLength));
+    }
+
+    #[test]
+    fn test_color_from_tuple() {
+        let color: Color = (1, 2, 3).into();
+        assert_eq!(color, Color::rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn test_from_wraps_into_ansi_escape() {
+        let e: AnsiEscape = SgrAttribute::Bold.into();
+        assert_eq!(e, AnsiEscape::Sgr(SgrAttribute::Bold));
+        let e: AnsiEscape = CursorMove::Up(2).into();
+        assert_eq!(e, AnsiEscape::Cursor(CursorMove::Up(2)));
+        let e: AnsiEscape = Erase::Line(EraseMode::All).into();
+        assert_eq!(e, AnsiEscape::Erase(Erase::Line(EraseMode::All)));
+        let e: AnsiEscape = DeviceControl::HideCursor.into();
+        assert_eq!(e, AnsiEscape::Device(DeviceControl::HideCursor));
+    }
+
+    #[test]
+    fn test_standard_16_distinct_and_ordered() {
+        let colors = Color::standard_16();
+        assert_eq!(colors[0], Color::Black);
+        assert_eq!(colors[15], Color::BrightWhite);
+        let distinct: BTreeSet<Color> = colors.into_iter().collect();
+        assert_eq!(distinct.len(), 16);
+        for (idx, color) in colors.into_iter().enumerate() {
+            assert_eq!(color.to_ansi256(), idx as u8);
+        }
+    }
+
+    #[test]
+    fn test_all_256_yields_every_index() {
+        assert_eq!(Color::all_256().count(), 256);
+        assert_eq!(Color::all_256().next(), Some(Color::AnsiValue(0)));
+    }
+
+    #[test]
+    fn test_to_ansi256_pure_red() {
+        assert_eq!(Color::Rgb24 { r: 255, g: 0, b: 0 }.to_ansi256(), 196);
+    }
+
+    #[test]
+    fn test_to_ansi256_pure_white() {
+        assert_eq!(
+            Color::Rgb24 {
+                r: 255,
+                g: 255,
+                b: 255
+            }
+            .to_ansi256(),
+            231
+        );
+    }
+
+    #[test]
+    fn test_to_ansi256_mid_gray_uses_grayscale_ramp() {
+        let idx = Color::Rgb24 {
+            r: 128,
+            g: 128,
+            b: 128,
+        }
+        .to_ansi256();
+        assert!((232..=255).contains(&idx), "got {idx}");
+        assert_eq!(idx, 244);
+    }
+
+    #[test]
+    fn test_to_ansi256_named_and_8bit() {
+        assert_eq!(Color::Black.to_ansi256(), 0);
+        assert_eq!(Color::White.to_ansi256(), 7);
+        assert_eq!(Color::BrightWhite.to_ansi256(), 15);
+        assert_eq!(Color::AnsiValue(123).to_ansi256(), 123);
+    }
+
+    #[test]
+    fn test_style_not_blinking() {
+        let blinking = Style::new()
+            .with(SgrAttribute::BlinkSlow)
+            .with(SgrAttribute::BlinkRapid);
+        assert!(blinking.clone().with(SgrAttribute::NotBlinking).is_empty());
+        // Dropping blinking uses NotBlinking (25)
+        assert!(!Style::reset_needed(&blinking, &Style::new()));
+    }
+
+    #[test]
+    fn test_style_not_framed_encircled() {
+        let framed = Style::new()
+            .with(SgrAttribute::Framed)
+            .with(SgrAttribute::Encircled)
+            .with(SgrAttribute::Bold);
+        let off = framed.clone().with(SgrAttribute::NotFramedEncircled);
+        assert_eq!(off.attrs().collect::<Vec<_>>(), [&SgrAttribute::Bold]);
+        assert!(!Style::reset_needed(&framed, &off));
+    }
+
+    #[test]
+    fn test_style_underline_none() {
+        let off = SgrAttribute::UnderlineStyle(UnderlineKind::None);
+        assert!(
+            Style::new()
+                .with(SgrAttribute::Underline)
+                .with(off)
+                .is_empty()
+        );
+        let curly = Style::new()
+            .with(SgrAttribute::UnderlineStyle(UnderlineKind::Curly))
+            .with(SgrAttribute::UnderlineColor(Color::Red));
+        assert_eq!(
+            curly.with(off).attrs().collect::<Vec<_>>(),
+            [&SgrAttribute::UnderlineColor(Color::Red)]
+        );
+    }
+
+    #[test]
+    fn test_style_is_empty() {
+        assert!(Style::new().is_empty());
+        let bold = Style::new().with(SgrAttribute::Bold);
+        assert!(!bold.is_empty());
+        assert!(bold.with(SgrAttribute::Reset).is_empty());
+        let overline = Style::new().with(SgrAttribute::Overline);
+        assert!(overline.with(SgrAttribute::NotOverline).is_empty());
+        let red = Style::new().with(SgrAttribute::Foreground(Color::Red));
+        assert!(red.with(SgrAttribute::DefaultForeground).is_empty());
+    }
+
+    #[test]
+    fn test_style_with_replaces_same_kind() {
+        let style = Style::new()
+            .with(SgrAttribute::Foreground(Color::Red))
+            .with(SgrAttribute::Foreground(Color::Green));
+        assert_eq!(
+            style.attrs().copied().collect::<Vec<_>>(),
+            vec![SgrAttribute::Foreground(Color::Green)]
+        );
+    }
+
+    #[test]
+    fn test_modifiers_param_round_trip() {
+        for param in 1..=16 {
+            assert_eq!(Modifiers::from_param(param).to_param(), param);
+        }
+        assert!(Modifiers::from_param(0).is_empty());
+        assert!(Modifiers::from_param(2).shift);
+    }
+
+    #[test]
+    fn test_kitty_keyboard_flags() {
+        let mut flags = KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+            | KittyKeyboardFlags::REPORT_ALTERNATE_KEYS;
+        assert_eq!(flags.bits(), 5);
+        assert!(flags.contains(KittyKeyboardFlags::REPORT_ALTERNATE_KEYS));
+        assert!(!flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES));
+        flags.remove(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES);
+        flags |= KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT;
+        assert_eq!(flags, KittyKeyboardFlags::from_bits(20));
+        assert!(flags.contains(KittyKeyboardFlags::empty()));
+    }
+
+    #[test]
+    fn test_style_underline_styles_share_slot() {
+        let curly = SgrAttribute::UnderlineStyle(UnderlineKind::Curly);
+        let style = Style::new().with(SgrAttribute::Underline).with(curly);
+        assert_eq!(style.attrs().copied().collect::<Vec<_>>(), vec![curly]);
+        let style = style.with(SgrAttribute::Underline);
+        assert_eq!(
+            style.attrs().copied().collect::<Vec<_>>(),
+            vec![SgrAttribute::Underline]
+        );
+    }
+
+    #[test]
+    fn test_style_reset_needed() {
+        let red = Style::new().with(SgrAttribute::Foreground(Color::Red));
+        let green = Style::new().with(SgrAttribute::Foreground(Color::Green));
+        let bold_red = red.clone().with(SgrAttribute::Bold);
+        let overline = Style::new().with(SgrAttribute::Overline);
+        // Dropping bold: there is no modelled "normal intensity" (22) code
+        assert!(Style::reset_needed(&bold_red, &red));
+        // Dropping a color uses DefaultForeground (39) / DefaultBackground (49)
+        assert!(!Style::reset_needed(&red, &Style::new()));
+        let blue_bg = Style::new().with(SgrAttribute::Background(Color::Blue));
+        assert!(!Style::reset_needed(&blue_bg, &Style::new()));
+        // ...and DefaultUnderlineColor (59)
+        let ul = Style::new().with(SgrAttribute::UnderlineColor(Color::Red));
+        assert!(!Style::reset_needed(&ul, &Style::new()));
+        // Dropping underline uses UnderlineStyle(UnderlineKind::None) (4:0)
+        let underline = Style::new().with(SgrAttribute::Underline);
+        assert!(!Style::reset_needed(&underline, &Style::new()));
+        // Dropping italic has no modelled disable code
+        let italic = Style::new().with(SgrAttribute::Italic);
+        assert!(Style::reset_needed(&italic, &Style::new()));
+        // Replacing a color with another
+        assert!(!Style::reset_needed(&red, &green));
+        // Adding attributes
+        assert!(!Style::reset_needed(&red, &bold_red));
+        assert!(!Style::reset_needed(&Style::new(), &bold_red));
+        // Dropping overline uses NotOverline (55)
+        assert!(!Style::reset_needed(&overline, &Style::new()));
+        // No change
+        assert!(!Style::reset_needed(&bold_red, &bold_red));
+    }
+
+    #[test]
+    fn test_sgr_params_each_role() {
+        let rgb = Color::rgb(10, 20, 30);
+        let cases = [
+            (Color::Red, ColorRole::Foreground, vec![31]),
+            (Color::Red, ColorRole::Background, vec![41]),
+            (Color::Red, ColorRole::Underline, vec![58, 5, 1]),
+            (Color::BrightWhite, ColorRole::Foreground, vec![97]),
+            (Color::BrightWhite, ColorRole::Background, vec![107]),
+            (Color::BrightWhite, ColorRole::Underline, vec![58, 5, 15]),
+            (Color::AnsiValue(99), ColorRole::Foreground, vec![38, 5, 99]),
+            (Color::AnsiValue(99), ColorRole::Background, vec![48, 5, 99]),
+            (Color::AnsiValue(99), ColorRole::Underline, vec![58, 5, 99]),
+            (rgb, ColorRole::Foreground, vec![38, 2, 10, 20, 30]),
+            (rgb, ColorRole::Background, vec![48, 2, 10, 20, 30]),
+            (rgb, ColorRole::Underline, vec![58, 2, 10, 20, 30]),
+        ];
+        for (color, role, expected) in cases {
+            assert_eq!(color.sgr_params(role), expected, "{:?} {:?}", color, role);
+        }
+    }
+
+    #[test]
+    fn test_to_basic16_pure_red() {
+        let color = Color::Rgb24 { r: 255, g: 0, b: 0 }.to_basic16();
+        assert!(
+            matches!(color, Color::Red | Color::BrightRed),
+            "got {color:?}"
+        );
+    }
+
+    #[test]
+    fn test_to_basic16_dark_gray() {
+        assert_eq!(
+            Color::Rgb24 {
+                r: 100,
+                g: 100,
+                b: 100
+            }
+            .to_basic16(),
+            Color::BrightBlack
+        );
+        assert_eq!(Color::AnsiValue(244).to_basic16(), Color::BrightBlack);
+    }
+
+    #[test]
+    fn test_alt_screen_mode_metadata() {
+        let cases = [
+            (47, AltScreenMode::Plain, false, false),
+            (1047, AltScreenMode::ClearOnExit, false, true),
+            (1049, AltScreenMode::SaveCursor, true, false),
+        ];
+        for (number, mode, saves_cursor, clears_on_exit) in cases {
+            assert_eq!(AltScreenMode::from_mode(number), Some(mode));
+            assert_eq!(mode.mode(), number);
+            assert_eq!(mode.saves_cursor(), saves_cursor);
+            assert_eq!(mode.clears_on_exit(), clears_on_exit);
+        }
+        assert_eq!(AltScreenMode::from_mode(1048), None);
+        let device = DeviceControl::AltScreen {
+            mode: AltScreenMode::ClearOnExit,
+            enabled: false,
+        };
+        assert_eq!(device.alt_screen_mode(), Some(AltScreenMode::ClearOnExit));
+        assert_eq!(
+            DeviceControl::ExitAltScreen.alt_screen_mode(),
+            Some(AltScreenMode::SaveCursor)
+        );
+    }
+
+    #[test]
+    fn test_to_basic16_named_unchanged() {
+        assert_eq!(Color::Cyan.to_basic16(), Color::Cyan);
+        assert_eq!(Color::AnsiValue(1).to_basic16(), Color::Red);
+    }
+}
@@ -1,7 +1,10 @@
 #![allow(dead_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
 //! Library root for ANSIEscapeRS.
 //! Exposes the `ansi_escape` module.
 
+extern crate alloc;
+
 pub mod ansi_escape;
 #[allow(unused_imports)]
 pub use ansi_escape::*;
@@ -0,0 +1,63 @@
+//! Exercises the API available without the `std` feature.
+//!
+//! Run with `cargo test --no-default-features --test no_std`.
+
+use ansi_escapers::{
+    AnsiCreator, AnsiEnvironment, Color, CursorMove, Erase, EraseMode, SgrAttribute,
+    parse_ansi_annotated,
+};
+
+fn creator() -> AnsiCreator {
+    AnsiCreator::with_environment(AnsiEnvironment {
+        supports_ansi: true,
+        supports_truecolor: true,
+        supports_8bit_color: true,
+        ..AnsiEnvironment::default()
+    })
+}
+
+#[test]
+fn sgr_code_without_std() {
+    let creator = creator();
+    assert_eq!(creator.sgr_code(SgrAttribute::Bold), "\x1B[1m");
+    assert_eq!(
+        creator.sgr_code(SgrAttribute::Foreground(Color::Rgb24 { r: 1, g: 2, b: 3 })),
+        "\x1B[38;2;1;2;3m"
+    );
+    assert_eq!(
+        creator.cursor_code(CursorMove::Position { row: 2, col: 3 }),
+        "\x1B[2;3H"
+    );
+}
+
+#[test]
+fn sequence_builder_without_std() {
+    let out = creator()
+        .sequence()
+        .move_to(1, 1)
+        .sgr(&[SgrAttribute::Bold])
+        .text("hi")
+        .erase(Erase::Line(EraseMode::ToEnd))
+        .build();
+    assert_eq!(out, "\x1B[1;1H\x1B[1mhi\x1B[0K");
+}
+
+#[test]
+fn default_environment_is_plain() {
+    let creator = AnsiCreator::with_environment(AnsiEnvironment::default());
+    assert_eq!(
+        creator
+            .sequence()
+            .sgr(&[SgrAttribute::Bold])
+            .text("x")
+            .build(),
+        "x"
+    );
+}
+
+#[test]
+fn parser_without_std() {
+    let result = parse_ansi_annotated("a\x1B[1mb\x1B[0m");
+    assert_eq!(result.text, "ab");
+    assert_eq!(result.spans[0].codes, vec![SgrAttribute::Bold]);
+}
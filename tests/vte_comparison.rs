@@ -0,0 +1,102 @@
+//! Compares the cleaned text of `parse_ansi_annotated` with what the `vte` crate's
+//! parser prints, as a check on how sequences are delimited.
+//!
+//! Intentional differences, asserted separately in `intentional_differences`:
+//!
+//! * REP (`ESC [ n b`) is expanded into the text; vte leaves it to the performer.
+//! * The C1 CSI character U+009B starts a sequence; vte prints it as a character.
+//! * An ESC that does not start a recognized sequence is kept as text (unless
+//!   `strip_lone_esc` is set), as are unterminated OSC and DCS strings. vte drops them.
+//! * Two-byte escapes this crate does not model, such as SS3 (`ESC O`), are kept as text.
+//! * C0 controls inside a CSI sequence are dropped with it; vte executes them.
+
+use ansi_escapers::parse_ansi_annotated;
+
+/// Collects printed characters and executed control bytes, the text a terminal would get.
+struct Text(String);
+
+impl vte::Perform for Text {
+    fn print(&mut self, c: char) {
+        self.0.push(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        self.0.push(byte as char);
+    }
+}
+
+fn vte_text(input: &str) -> String {
+    let mut parser = vte::Parser::new();
+    let mut text = Text(String::new());
+    parser.advance(&mut text, input.as_bytes());
+    text.0
+}
+
+const CORPUS: &[&str] = &[
+    "",
+    "plain text",
+    "line one\nline two\r\n",
+    "tab\there\x08\x07bell",
+    "h\u{e9}llo \x1B[1mw\u{f6}rld\x1B[0m \u{65e5}\u{672c}",
+    // CSI
+    "\x1B[1;31mred\x1B[0m",
+    "\x1B[38;5;196mx\x1B[38;2;1;2;3my\x1B[4:3mz\x1B[38:2::1:2:3mw",
+    "\x1B[2J\x1B[H\x1B[10;20Hpos\x1B[K",
+    "\x1B[?25l\x1B[?1049hscreen\x1B[?1049l\x1B[?25h",
+    "\x1B[?2004h\x1B[200~paste\x1B[201~",
+    "\x1B[!psoft\x1B[1 qcursor\x1B[>1uflags\x1B[<u",
+    "\x1B[<0;10;5Mmouse\x1B[<0;10;5m",
+    "\x1B[=5hmode\x1B[6n\x1B[3;4R",
+    "\x1B[999999999999mbig",
+    "\x1B[mempty\x1B[;mparams",
+    "truncated\x1B[1;3",
+    // CSI interrupted by CAN, SUB or another ESC
+    "cancel\x1B[12\x18after",
+    "sub\x1B[12\x1Aafter",
+    "\x1B[1;3\x1B[0mnested",
+    // OSC, terminated by BEL or ST
+    "\x1B]0;title\x07after",
+    "\x1B]2;title\x1B\\after",
+    "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x1B\\",
+    "\x1B]52;c;aGk=\x07clip",
+    "\x1B]133;A\x07prompt\x1B]133;D;0\x1B\\",
+    // DCS, APC, PM and SOS strings
+    "\x1BPq#0;2;0;0;0\x1B\\dcs",
+    "\x1B_apc\x1B\\after",
+    "\x1B^pm\x1B\\after",
+    "\x1BXsos\x1B\\after",
+    // Two-byte and nF escapes
+    "\x1B7save\x1B8\x1BMindex\x1BHtab\x1Bc",
+    "\x1B=keypad\x1B>",
+    "\x1B(Bcharset\x1B)0\x1B#8align",
+];
+
+#[test]
+fn cleaned_text_matches_vte() {
+    for input in CORPUS {
+        assert_eq!(
+            parse_ansi_annotated(input).text,
+            vte_text(input),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn intentional_differences() {
+    let cases = [
+        ("a\x1B[3bb", "aaaab", "ab"),
+        ("\u{9B}31mc1\u{9B}0m", "c1", "\u{9B}31mc1\u{9B}0m"),
+        ("lone\x1B", "lone\x1B", "lone"),
+        ("\x1Bx", "\x1Bx", ""),
+        ("\x1B]0;unterminated", "\x1B]0;unterminated", ""),
+        ("\x1BOPss3", "\x1BOPss3", "Pss3"),
+        ("\x1B[1\n;31mx", "x", "\nx"),
+        ("\x1B]0;a\x1B[1mb", "\x1B]0;ab", "b"),
+    ];
+    for (input, ours, theirs) in cases {
+        assert_eq!(parse_ansi_annotated(input).text, ours, "{:?}", input);
+        assert_eq!(vte_text(input), theirs, "{:?}", input);
+    }
+}